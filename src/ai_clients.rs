@@ -1,16 +1,40 @@
-use crate::types::{Message, MessageRole};
+use crate::config::Config;
+use crate::media::{PreparedAudio, VisionImage};
+use crate::metrics::observe_provider;
+use crate::types::{ChatConfig, Message, MessageRole};
 use anyhow::{Context, Result};
 use base64::Engine;
-use reqwest::Client;
+use reqwest::{multipart, Client};
 use serde::{Deserialize, Serialize};
-use std::time::Duration;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
 use tracing::{debug, error, info};
 
-#[derive(Debug, Clone, Serialize)]
-#[serde(untagged)]
-pub enum OpenAIContent {
-    Text(String),
-    Vision(Vec<OpenAIContentPart>),
+// gpt-4o supports vision
+const OPENAI_CHAT_MODEL: &str = "gpt-4o";
+
+/// Small model that scores whether a mention is meant for the bot.
+const OPENAI_INTENT_MODEL: &str = "gpt-4o-mini";
+
+/// Model used to generate and edit images.
+pub const OPENAI_IMAGE_MODEL: &str = "gpt-image-1";
+
+/// Tokens a completion used, as reported by the provider.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TokenUsage {
+    pub prompt_tokens: i64,
+    pub completion_tokens: i64,
+}
+
+/// A chat completion's text along with who wrote it, how long it took and
+/// the tokens it used, when the provider says.
+#[derive(Debug, Clone)]
+pub struct ChatCompletion {
+    pub text: String,
+    pub provider: &'static str,
+    pub model: String,
+    pub usage: Option<TokenUsage>,
+    pub latency_ms: i64,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -56,6 +80,13 @@ pub struct OpenAIFunction {
 #[derive(Debug, Clone, Deserialize)]
 pub struct OpenAIChatResponse {
     pub choices: Vec<OpenAIChoice>,
+    pub usage: Option<OpenAIUsage>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct OpenAIUsage {
+    pub prompt_tokens: i64,
+    pub completion_tokens: i64,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -85,6 +116,8 @@ pub struct OpenAIFunctionCall {
 pub struct OllamaMessage {
     pub role: String,
     pub content: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub images: Option<Vec<String>>,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -92,11 +125,19 @@ pub struct OllamaChatRequest {
     pub model: String,
     pub messages: Vec<OllamaMessage>,
     pub stream: bool,
+    pub options: OllamaOptions,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OllamaOptions {
+    pub temperature: f32,
 }
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct OllamaChatResponse {
     pub message: OllamaResponseMessage,
+    pub prompt_eval_count: Option<i64>,
+    pub eval_count: Option<i64>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -126,16 +167,66 @@ pub struct ImageData {
     pub b64_json: Option<String>,
 }
 
+// Text-to-speech structures
+#[derive(Debug, Clone, Serialize)]
+pub struct SpeechRequest {
+    pub model: String,
+    pub input: String,
+    pub voice: String,
+    pub response_format: String,
+}
+
+// Speech-to-text structures
+#[derive(Debug, Clone, Deserialize)]
+pub struct TranscriptionResponse {
+    pub text: String,
+}
+
+/// Per-request model settings, usually taken from a chat's config.
+#[derive(Debug, Clone)]
+pub struct CompletionOptions {
+    pub use_ollama: bool,
+    pub model: Option<String>,
+    pub temperature: f32,
+}
+
+impl CompletionOptions {
+    pub fn from_chat_config(config: &ChatConfig) -> Self {
+        Self {
+            use_ollama: config.use_ollama,
+            model: config.model().map(str::to_string),
+            temperature: config.temperature,
+        }
+    }
+}
+
+impl Default for CompletionOptions {
+    fn default() -> Self {
+        Self {
+            use_ollama: false,
+            model: None,
+            temperature: 0.7,
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct AIClients {
     http_client: Client,
     openai_api_key: Option<String>,
     ollama_api: String,
     ollama_model: String,
+    stt_api: String,
+    stt_api_key: Option<String>,
+    stt_model: String,
+    tts_api: String,
+    tts_api_key: Option<String>,
+    tts_model: String,
+    tts_voice: String,
 }
 
 impl AIClients {
-    pub fn new(openai_api_key: Option<String>, ollama_api: String, ollama_model: String) -> Self {
+    pub fn new(config: &Config) -> Self {
         let http_client = Client::builder()
             .timeout(Duration::from_secs(60))
             .build()
@@ -143,30 +234,92 @@ impl AIClients {
 
         Self {
             http_client,
-            openai_api_key,
-            ollama_api,
-            ollama_model,
+            openai_api_key: config.openai_api_key.clone(),
+            ollama_api: config.ollama_api.clone(),
+            ollama_model: config.ollama_model.clone(),
+            stt_api: config.stt_api.trim_end_matches('/').to_string(),
+            stt_api_key: config.stt_api_key.clone(),
+            stt_model: config.stt_model.clone(),
+            tts_api: config.tts_api.trim_end_matches('/').to_string(),
+            tts_api_key: config.tts_api_key.clone(),
+            tts_model: config.tts_model.clone(),
+            tts_voice: config.tts_voice.clone(),
         }
     }
 
+    /// `images` maps attachment guids to prepared images; each image is sent
+    /// alongside the message in `messages` that references it.
     pub async fn generate_chat_completion(
         &self,
         messages: &[Message],
         system_prompt: &str,
-        use_ollama: bool,
+        options: &CompletionOptions,
         include_image_tool: bool,
-        image_data: Option<Vec<u8>>,
-    ) -> Result<String> {
+        images: &HashMap<String, VisionImage>,
+    ) -> Result<ChatCompletion> {
+        if options.use_ollama {
+            let request = self.ollama_chat_completion(messages, system_prompt, options, images);
+            observe_provider("ollama", "chat", request).await
+        } else {
+            let request =
+                self.openai_chat_completion(messages, system_prompt, options, include_image_tool, images);
+            observe_provider("openai", "chat", request).await
+        }
+    }
+
+    /// The model used when a chat hasn't picked one.
+    pub fn default_model(&self, use_ollama: bool) -> &str {
         if use_ollama {
-            self.ollama_chat_completion(messages, system_prompt, image_data)
-                .await
+            &self.ollama_model
         } else {
-            self.openai_chat_completion(messages, system_prompt, include_image_tool, image_data)
-                .await
+            OPENAI_CHAT_MODEL
         }
     }
 
-    pub async fn generate_character_prompt(&self, description: &str) -> Result<String> {
+    /// Asks a small model how likely it is that a message mentioning the bot
+    /// is actually addressed to it. Returns a confidence from 0 to 1, with
+    /// the completion for usage tracking.
+    pub async fn classify_intent(
+        &self,
+        text: &str,
+        bot_name: &str,
+        use_ollama: bool,
+        model: Option<&str>,
+    ) -> Result<(f32, ChatCompletion)> {
+        let system_prompt = format!(
+            "You decide whether a group chat message is addressed to an AI assistant named \"{}\", \
+or only mentions it in passing. Reply with a single number from 0 to 1: the probability that \
+the sender wants the assistant to respond. Reply with the number only.",
+            bot_name
+        );
+
+        let messages = vec![Message {
+            role: MessageRole::User,
+            content: text.to_string(),
+            timestamp: chrono::Utc::now(),
+            attachments: Vec::new(),
+        }];
+
+        let options = CompletionOptions {
+            use_ollama,
+            model: Some(match model {
+                Some(model) => model.to_string(),
+                None if use_ollama => self.ollama_model.clone(),
+                None => OPENAI_INTENT_MODEL.to_string(),
+            }),
+            temperature: 0.0,
+        };
+
+        let completion = self
+            .generate_chat_completion(&messages, &system_prompt, &options, false, &HashMap::new())
+            .await?;
+
+        let confidence = parse_confidence(&completion.text)
+            .ok_or_else(|| anyhow::anyhow!("Intent classifier gave no score: {}", completion.text))?;
+        Ok((confidence, completion))
+    }
+
+    pub async fn generate_character_prompt(&self, description: &str) -> Result<ChatCompletion> {
         let system_prompt = "You are a prompt engineer. Generate a detailed system prompt for an AI character based on the user's description. The prompt should:
 1. Define the character's personality, mannerisms, and speaking style
 2. Include specific behavioral traits and quirks
@@ -179,26 +332,31 @@ Keep it concise but comprehensive. Return only the system prompt, nothing else."
             role: MessageRole::User,
             content: description.to_string(),
             timestamp: chrono::Utc::now(),
+            attachments: Vec::new(),
         }];
 
-        let prompt = if self.openai_api_key.is_some() {
-            self.openai_chat_completion(&messages, system_prompt, false, None)
-                .await?
+        let options = CompletionOptions::default();
+        let images = HashMap::new();
+        let mut completion = if self.openai_api_key.is_some() {
+            let request = self.openai_chat_completion(&messages, system_prompt, &options, false, &images);
+            observe_provider("openai", "chat", request).await?
         } else {
-            self.ollama_chat_completion(&messages, system_prompt, None)
-                .await?
+            let request = self.ollama_chat_completion(&messages, system_prompt, &options, &images);
+            observe_provider("ollama", "chat", request).await?
         };
 
-        Ok(prompt.trim().to_string())
+        completion.text = completion.text.trim().to_string();
+        Ok(completion)
     }
 
     async fn openai_chat_completion(
         &self,
         messages: &[Message],
         system_prompt: &str,
+        options: &CompletionOptions,
         include_image_tool: bool,
-        image_data: Option<Vec<u8>>,
-    ) -> Result<String> {
+        images: &HashMap<String, VisionImage>,
+    ) -> Result<ChatCompletion> {
         let api_key = self
             .openai_api_key
             .as_ref()
@@ -221,70 +379,44 @@ Keep it concise but comprehensive. Return only the system prompt, nothing else."
                 MessageRole::Assistant => "assistant",
                 MessageRole::System => "system",
             };
-            openai_messages.push(OpenAIMessage {
-                role: role.to_string(),
-                content: vec![OpenAIContentPart::Text {
-                    text: message.content.clone(),
-                }],
-            });
-        }
 
-        let has_image = image_data.is_some();
+            let mut content = vec![OpenAIContentPart::Text {
+                text: message.content.clone(),
+            }];
 
-        // Add image if provided - create a separate user message with vision content
-        if let Some(image_bytes) = image_data {
-            let base64_image = base64::engine::general_purpose::STANDARD.encode(&image_bytes);
-            let data_url = format!("data:image/jpeg;base64,{}", base64_image);
+            // Attach images where they were shared in the conversation
+            for image in message_images(message, images) {
+                content.push(OpenAIContentPart::ImageUrl {
+                    image_url: OpenAIImageUrl {
+                        url: image.data_url(),
+                    },
+                });
+            }
 
-            // Add a new user message specifically for image analysis
             openai_messages.push(OpenAIMessage {
-                role: "user".to_string(),
-                content: vec![
-                    OpenAIContentPart::Text {
-                        text: "what's in this image?".to_string(),
-                    },
-                    OpenAIContentPart::ImageUrl {
-                        image_url: OpenAIImageUrl { url: data_url },
-                    },
-                ],
+                role: role.to_string(),
+                content,
             });
         }
 
-        let model = if has_image {
-            "gpt-4o".to_string() // gpt-4o supports vision
-        } else {
-            "gpt-4o".to_string()
-        };
-
+        let model = options
+            .model
+            .clone()
+            .unwrap_or_else(|| OPENAI_CHAT_MODEL.to_string());
         let mut request = OpenAIChatRequest {
-            model,
+            model: model.clone(),
             messages: openai_messages,
-            temperature: 0.7,
+            temperature: options.temperature,
             tools: None,
         };
 
         if include_image_tool {
-            request.tools = Some(vec![OpenAITool {
-                tool_type: "function".to_string(),
-                function: OpenAIFunction {
-                    name: "request_picture".to_string(),
-                    description: "Generate and send a picture to the chat using DALL-E".to_string(),
-                    parameters: serde_json::json!({
-                        "type": "object",
-                        "properties": {
-                            "description": {
-                                "type": "string",
-                                "description": "Detailed description of the picture to generate using DALL-E"
-                            }
-                        },
-                        "required": ["description"]
-                    }),
-                },
-            }]);
+            request.tools = Some(chat_tools());
         }
 
         debug!("Sending OpenAI chat completion request");
 
+        let started = Instant::now();
         let response = self
             .http_client
             .post("https://api.openai.com/v1/chat/completions")
@@ -307,38 +439,48 @@ Keep it concise but comprehensive. Return only the system prompt, nothing else."
             .await
             .context("Failed to parse OpenAI response")?;
 
-        if let Some(choice) = chat_response.choices.first() {
-            if let Some(tool_calls) = &choice.message.tool_calls {
-                // Handle tool calls (image generation)
-                for tool_call in tool_calls {
-                    if tool_call.function.name == "request_picture" {
-                        return Ok(format!(
-                            "[TOOL_CALL:request_picture:{}]",
-                            tool_call.function.arguments
-                        ));
-                    }
-                }
-            }
+        let usage = chat_response.usage.map(|usage| TokenUsage {
+            prompt_tokens: usage.prompt_tokens,
+            completion_tokens: usage.completion_tokens,
+        });
+        let choice = chat_response
+            .choices
+            .first()
+            .ok_or_else(|| anyhow::anyhow!("No choices in OpenAI response"))?;
+
+        // Hand the first tool call back to the agent to run
+        let text = match choice.message.tool_calls.as_ref().and_then(|calls| calls.first()) {
+            Some(tool_call) => format!(
+                "[TOOL_CALL:{}:{}]",
+                tool_call.function.name, tool_call.function.arguments
+            ),
+            None => choice.message.content.clone().unwrap_or_default(),
+        };
 
-            Ok(choice.message.content.clone().unwrap_or_default())
-        } else {
-            Err(anyhow::anyhow!("No choices in OpenAI response"))
-        }
+        Ok(ChatCompletion {
+            text,
+            provider: "openai",
+            model,
+            usage,
+            latency_ms: started.elapsed().as_millis() as i64,
+        })
     }
 
     async fn ollama_chat_completion(
         &self,
         messages: &[Message],
         system_prompt: &str,
-        image_data: Option<Vec<u8>>,
-    ) -> Result<String> {
+        options: &CompletionOptions,
+        images: &HashMap<String, VisionImage>,
+    ) -> Result<ChatCompletion> {
         let mut ollama_messages = vec![OllamaMessage {
             role: "system".to_string(),
-            content: if image_data.is_some() {
-                format!("{} If you want to generate and send a picture, just say [REQUEST_PICTURE] followed by your description. Note: An image was uploaded but Ollama vision support is limited.", system_prompt)
+            content: if !images.is_empty() {
+                format!("{} If you want to generate and send a picture, just say [REQUEST_PICTURE] followed by your description. Note: Images were shared but Ollama vision support depends on the model.", system_prompt)
             } else {
                 format!("{} If you want to generate and send a picture, just say [REQUEST_PICTURE] followed by your description.", system_prompt)
             },
+            images: None,
         }];
 
         for message in messages {
@@ -347,16 +489,33 @@ Keep it concise but comprehensive. Return only the system prompt, nothing else."
                 MessageRole::Assistant => "assistant",
                 MessageRole::System => "system",
             };
+            // Ollama takes raw base64 images on the message (vision models only)
+            let message_images: Vec<String> = message_images(message, images)
+                .map(|image| base64::engine::general_purpose::STANDARD.encode(&image.data))
+                .collect();
+
             ollama_messages.push(OllamaMessage {
                 role: role.to_string(),
                 content: message.content.clone(),
+                images: if message_images.is_empty() {
+                    None
+                } else {
+                    Some(message_images)
+                },
             });
         }
 
+        let model = options
+            .model
+            .clone()
+            .unwrap_or_else(|| self.ollama_model.clone());
         let request = OllamaChatRequest {
-            model: self.ollama_model.clone(),
+            model: model.clone(),
             messages: ollama_messages,
             stream: false,
+            options: OllamaOptions {
+                temperature: options.temperature,
+            },
         };
 
         debug!(
@@ -364,9 +523,10 @@ Keep it concise but comprehensive. Return only the system prompt, nothing else."
             self.ollama_api
         );
 
+        let started = Instant::now();
         let response = self
             .http_client
-            .post(&format!("{}/api/chat", self.ollama_api))
+            .post(format!("{}/api/chat", self.ollama_api))
             .json(&request)
             .send()
             .await
@@ -384,28 +544,45 @@ Keep it concise but comprehensive. Return only the system prompt, nothing else."
             .await
             .context("Failed to parse Ollama response")?;
 
-        let content = chat_response.message.content;
+        let usage = match (chat_response.prompt_eval_count, chat_response.eval_count) {
+            (None, None) => None,
+            (prompt_tokens, completion_tokens) => Some(TokenUsage {
+                prompt_tokens: prompt_tokens.unwrap_or(0),
+                completion_tokens: completion_tokens.unwrap_or(0),
+            }),
+        };
+        let mut text = chat_response.message.content;
 
         // Check for picture generation request
-        if content.contains("[REQUEST_PICTURE]") {
-            let description = content.replace("[REQUEST_PICTURE]", "").trim().to_string();
-            return Ok(format!(
+        if text.contains("[REQUEST_PICTURE]") {
+            let description = text.replace("[REQUEST_PICTURE]", "").trim().to_string();
+            text = format!(
                 "[TOOL_CALL:request_picture:{}]",
-                serde_json::json!({"description": description}).to_string()
-            ));
+                serde_json::json!({"description": description})
+            );
         }
 
-        Ok(content)
+        Ok(ChatCompletion {
+            text,
+            provider: "ollama",
+            model,
+            usage,
+            latency_ms: started.elapsed().as_millis() as i64,
+        })
     }
 
     pub async fn generate_image(&self, description: &str) -> Result<Vec<u8>> {
+        observe_provider("openai", "image", self.request_image(description)).await
+    }
+
+    async fn request_image(&self, description: &str) -> Result<Vec<u8>> {
         let api_key = self
             .openai_api_key
             .as_ref()
             .ok_or_else(|| anyhow::anyhow!("OpenAI API key required for image generation"))?;
 
         let request = ImageGenerationRequest {
-            model: "gpt-image-1".to_string(),
+            model: OPENAI_IMAGE_MODEL.to_string(),
             prompt: description.to_string(),
             n: 1,
             size: "1024x1024".to_string(),
@@ -413,7 +590,7 @@ Keep it concise but comprehensive. Return only the system prompt, nothing else."
             response_format: "b64_json".to_string(),
         };
 
-        debug!("Generating image with gpt-image-1: {}", description);
+        debug!("Generating image with {}: {}", OPENAI_IMAGE_MODEL, description);
 
         let response = self
             .http_client
@@ -442,6 +619,172 @@ Keep it concise but comprehensive. Return only the system prompt, nothing else."
             .first()
             .ok_or_else(|| anyhow::anyhow!("No image data in response"))?;
 
+        self.image_data_bytes(image_data).await
+    }
+
+    /// Edits a photo according to `instruction` using the images edit
+    /// endpoint. Returns `count` results, so it also produces variations.
+    pub async fn edit_image(
+        &self,
+        image: &VisionImage,
+        instruction: &str,
+        count: u32,
+    ) -> Result<Vec<Vec<u8>>> {
+        let request = self.request_image_edit(image, instruction, count);
+        observe_provider("openai", "image_edit", request).await
+    }
+
+    async fn request_image_edit(
+        &self,
+        image: &VisionImage,
+        instruction: &str,
+        count: u32,
+    ) -> Result<Vec<Vec<u8>>> {
+        let api_key = self
+            .openai_api_key
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("OpenAI API key required for image editing"))?;
+
+        let extension = image.mime_type.strip_prefix("image/").unwrap_or("png");
+        let form = multipart::Form::new()
+            .text("model", OPENAI_IMAGE_MODEL)
+            .text("prompt", instruction.to_string())
+            .text("n", count.to_string())
+            .text("size", "auto")
+            .part(
+                "image",
+                multipart::Part::bytes(image.data.clone())
+                    .file_name(format!("image.{}", extension))
+                    .mime_str(&image.mime_type)?,
+            );
+
+        debug!("Editing image with {}: {}", OPENAI_IMAGE_MODEL, instruction);
+
+        let response = self
+            .http_client
+            .post("https://api.openai.com/v1/images/edits")
+            .header("Authorization", format!("Bearer {}", api_key))
+            .multipart(form)
+            .send()
+            .await
+            .context("Failed to send image edit request")?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let text = response.text().await.unwrap_or_default();
+            error!("Image edit failed with status {}: {}", status, text);
+            return Err(anyhow::anyhow!("Image edit failed: {}", text));
+        }
+
+        let image_response: ImageGenerationResponse = response
+            .json()
+            .await
+            .context("Failed to parse image edit response")?;
+
+        if image_response.data.is_empty() {
+            return Err(anyhow::anyhow!("No image data in response"));
+        }
+
+        let mut images = Vec::new();
+        for image_data in &image_response.data {
+            images.push(self.image_data_bytes(image_data).await?);
+        }
+        Ok(images)
+    }
+
+    /// Transcribes audio with an OpenAI-compatible `/audio/transcriptions`
+    /// endpoint (OpenAI or a local whisper server).
+    pub async fn transcribe_audio(&self, audio: PreparedAudio) -> Result<String> {
+        observe_provider("stt", "transcription", self.request_transcription(audio)).await
+    }
+
+    async fn request_transcription(&self, audio: PreparedAudio) -> Result<String> {
+        let form = multipart::Form::new()
+            .text("model", self.stt_model.clone())
+            .part(
+                "file",
+                multipart::Part::bytes(audio.data)
+                    .file_name(audio.filename)
+                    .mime_str(&audio.mime_type)?,
+            );
+
+        debug!("Transcribing audio with {} at {}", self.stt_model, self.stt_api);
+
+        let mut request = self
+            .http_client
+            .post(format!("{}/audio/transcriptions", self.stt_api))
+            .multipart(form);
+        if let Some(api_key) = &self.stt_api_key {
+            request = request.header("Authorization", format!("Bearer {}", api_key));
+        }
+
+        let response = request
+            .send()
+            .await
+            .context("Failed to send transcription request")?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let text = response.text().await.unwrap_or_default();
+            error!("Transcription failed with status {}: {}", status, text);
+            return Err(anyhow::anyhow!("Transcription failed: {}", text));
+        }
+
+        let transcription: TranscriptionResponse = response
+            .json()
+            .await
+            .context("Failed to parse transcription response")?;
+
+        info!("Transcribed audio ({} characters)", transcription.text.len());
+        Ok(transcription.text.trim().to_string())
+    }
+
+    /// Synthesizes speech with an OpenAI-compatible `/audio/speech` endpoint.
+    /// Returns MP3 audio.
+    pub async fn synthesize_speech(&self, text: &str, voice: Option<&str>) -> Result<Vec<u8>> {
+        observe_provider("tts", "speech", self.request_speech(text, voice)).await
+    }
+
+    async fn request_speech(&self, text: &str, voice: Option<&str>) -> Result<Vec<u8>> {
+        let request = SpeechRequest {
+            model: self.tts_model.clone(),
+            input: text.to_string(),
+            voice: voice.unwrap_or(&self.tts_voice).to_string(),
+            response_format: "mp3".to_string(),
+        };
+
+        debug!("Synthesizing speech with {} ({})", request.model, request.voice);
+
+        let mut http_request = self
+            .http_client
+            .post(format!("{}/audio/speech", self.tts_api))
+            .json(&request);
+        if let Some(api_key) = &self.tts_api_key {
+            http_request = http_request.header("Authorization", format!("Bearer {}", api_key));
+        }
+
+        let response = http_request
+            .send()
+            .await
+            .context("Failed to send speech request")?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let text = response.text().await.unwrap_or_default();
+            error!("Speech synthesis failed with status {}: {}", status, text);
+            return Err(anyhow::anyhow!("Speech synthesis failed: {}", text));
+        }
+
+        let audio = response
+            .bytes()
+            .await
+            .context("Failed to read speech audio")?;
+
+        info!("Synthesized speech ({} bytes)", audio.len());
+        Ok(audio.to_vec())
+    }
+
+    async fn image_data_bytes(&self, image_data: &ImageData) -> Result<Vec<u8>> {
         // Handle base64 response format
         if let Some(b64_json) = &image_data.b64_json {
             let image_bytes = base64::engine::general_purpose::STANDARD
@@ -449,7 +792,8 @@ Keep it concise but comprehensive. Return only the system prompt, nothing else."
                 .context("Failed to decode base64 image data")?;
 
             info!(
-                "Successfully generated image with gpt-image-1 ({} bytes)",
+                "Successfully generated image with {} ({} bytes)",
+                OPENAI_IMAGE_MODEL,
                 image_bytes.len()
             );
             Ok(image_bytes)
@@ -477,3 +821,149 @@ Keep it concise but comprehensive. Return only the system prompt, nothing else."
         }
     }
 }
+
+/// Tools the model can call during a chat completion.
+fn chat_tools() -> Vec<OpenAITool> {
+    vec![
+        OpenAITool {
+            tool_type: "function".to_string(),
+            function: OpenAIFunction {
+                name: "request_picture".to_string(),
+                description: "Generate and send a picture to the chat using DALL-E".to_string(),
+                parameters: serde_json::json!({
+                    "type": "object",
+                    "properties": {
+                        "description": {
+                            "type": "string",
+                            "description": "Detailed description of the picture to generate using DALL-E"
+                        }
+                    },
+                    "required": ["description"]
+                }),
+            },
+        },
+        OpenAITool {
+            tool_type: "function".to_string(),
+            function: OpenAIFunction {
+                name: "resend_image".to_string(),
+                description: "Send a picture you generated earlier in this chat again".to_string(),
+                parameters: serde_json::json!({
+                    "type": "object",
+                    "properties": {
+                        "index": {
+                            "type": "integer",
+                            "description": "Which generated picture to resend: 1 is the most recent, 2 the one before, and so on"
+                        }
+                    }
+                }),
+            },
+        },
+        OpenAITool {
+            tool_type: "function".to_string(),
+            function: OpenAIFunction {
+                name: "send_file".to_string(),
+                description: "Send a text-based file to the chat, such as a calendar invite (.ics), contact card (.vcf), list or notes".to_string(),
+                parameters: serde_json::json!({
+                    "type": "object",
+                    "properties": {
+                        "filename": {
+                            "type": "string",
+                            "description": "File name with extension, e.g. 'dinner.ics' or 'contact.vcf'"
+                        },
+                        "content": {
+                            "type": "string",
+                            "description": "Full text content of the file"
+                        }
+                    },
+                    "required": ["filename", "content"]
+                }),
+            },
+        },
+        OpenAITool {
+            tool_type: "function".to_string(),
+            function: OpenAIFunction {
+                name: "edit_image".to_string(),
+                description: "Edit the most recent photo someone shared in the chat (restyle it, change things, make variations) and send the result".to_string(),
+                parameters: serde_json::json!({
+                    "type": "object",
+                    "properties": {
+                        "instruction": {
+                            "type": "string",
+                            "description": "What to change about the photo, e.g. 'make it look like a watercolor painting'"
+                        },
+                        "count": {
+                            "type": "integer",
+                            "description": "How many variations to send (1-4, default 1)"
+                        }
+                    },
+                    "required": ["instruction"]
+                }),
+            },
+        },
+        OpenAITool {
+            tool_type: "function".to_string(),
+            function: OpenAIFunction {
+                name: "set_reminder".to_string(),
+                description: "Schedule a reminder message to be sent to this chat later".to_string(),
+                parameters: serde_json::json!({
+                    "type": "object",
+                    "properties": {
+                        "time": {
+                            "type": "string",
+                            "description": "When to send it, in the chat's local time as YYYY-MM-DDTHH:MM (see the current time in your instructions)"
+                        },
+                        "message": {
+                            "type": "string",
+                            "description": "What to remind the chat about, e.g. 'book the restaurant'"
+                        }
+                    },
+                    "required": ["time", "message"]
+                }),
+            },
+        },
+        OpenAITool {
+            tool_type: "function".to_string(),
+            function: OpenAIFunction {
+                name: "create_poll".to_string(),
+                description: "Start a poll so the group can vote on a decision, like where to eat or which day works".to_string(),
+                parameters: serde_json::json!({
+                    "type": "object",
+                    "properties": {
+                        "question": {
+                            "type": "string",
+                            "description": "The question to vote on"
+                        },
+                        "options": {
+                            "type": "array",
+                            "items": { "type": "string" },
+                            "description": "2 to 6 short answer options"
+                        },
+                        "hours": {
+                            "type": "integer",
+                            "description": "How many hours until the poll closes and results are announced (optional)"
+                        }
+                    },
+                    "required": ["question", "options"]
+                }),
+            },
+        },
+    ]
+}
+
+/// Pulls the first number out of a classifier reply, clamped to 0..=1.
+fn parse_confidence(reply: &str) -> Option<f32> {
+    reply
+        .split(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .find_map(|token| token.parse::<f32>().ok())
+        .map(|value| value.clamp(0.0, 1.0))
+}
+
+fn message_images<'a>(
+    message: &'a Message,
+    images: &'a HashMap<String, VisionImage>,
+) -> impl Iterator<Item = &'a VisionImage> {
+    message
+        .attachments
+        .iter()
+        .filter_map(|attachment| images.get(&attachment.guid))
+}
@@ -0,0 +1,239 @@
+use anyhow::{Context, Result};
+use chrono::Utc;
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+use tracing::{debug, info, warn};
+
+use crate::config::Config;
+use crate::database::Database;
+use crate::types::{BlueBubblesAttachment, CachedAttachment};
+
+/// How many entries eviction looks at per database round trip.
+const EVICTION_BATCH: i64 = 50;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttachmentSource {
+    Downloaded,
+    Generated,
+}
+
+impl AttachmentSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Downloaded => "downloaded",
+            Self::Generated => "generated",
+        }
+    }
+}
+
+/// On-disk cache for downloaded and generated attachments. Files are stored
+/// by content hash; metadata and access times live in SQLite so the cache can
+/// be trimmed least-recently-used first.
+#[derive(Clone)]
+pub struct AttachmentCache {
+    dir: PathBuf,
+    max_bytes: i64,
+    database: Database,
+}
+
+impl AttachmentCache {
+    pub fn new(dir: PathBuf, max_bytes: i64, database: Database) -> Result<Self> {
+        std::fs::create_dir_all(&dir).context("Failed to create attachment cache directory")?;
+        Ok(Self {
+            dir,
+            max_bytes,
+            database,
+        })
+    }
+
+    pub fn from_config(config: &Config, database: Database) -> Result<Self> {
+        Self::new(
+            PathBuf::from(&config.attachment_cache_dir),
+            config.attachment_cache_max_bytes,
+            database,
+        )
+    }
+
+    /// Returns the cached bytes for an attachment guid, if present.
+    pub async fn get(&self, attachment_guid: &str) -> Result<Option<(CachedAttachment, Vec<u8>)>> {
+        let Some(record) = self.database.get_cached_attachment(attachment_guid).await? else {
+            return Ok(None);
+        };
+
+        match tokio::fs::read(self.path_for(&record.content_hash)).await {
+            Ok(data) => {
+                self.database.touch_cached_attachment(attachment_guid).await?;
+                debug!("Attachment cache hit for {}", attachment_guid);
+                Ok(Some((record, data)))
+            }
+            Err(e) => {
+                // File was removed behind our back; forget the entry
+                warn!("Cached attachment {} is missing on disk: {}", attachment_guid, e);
+                self.database.delete_cached_attachment(attachment_guid).await?;
+                Ok(None)
+            }
+        }
+    }
+
+    pub async fn put(
+        &self,
+        chat_guid: &str,
+        attachment: &BlueBubblesAttachment,
+        data: &[u8],
+        source: AttachmentSource,
+        description: Option<&str>,
+    ) -> Result<CachedAttachment> {
+        let content_hash = content_hash(data);
+        let path = self.path_for(&content_hash);
+
+        if tokio::fs::metadata(&path).await.is_err() {
+            tokio::fs::write(&path, data)
+                .await
+                .context("Failed to write attachment to cache")?;
+        }
+
+        let record = CachedAttachment {
+            attachment_guid: attachment.guid.clone(),
+            chat_guid: chat_guid.to_string(),
+            content_hash,
+            mime_type: attachment.mime_type.clone(),
+            filename: attachment.transfer_name.clone(),
+            size_bytes: data.len() as i64,
+            source: source.as_str().to_string(),
+            description: description.map(|d| d.to_string()),
+            created_at: Utc::now(),
+        };
+        self.database.save_cached_attachment(&record).await?;
+
+        debug!(
+            "Cached {} attachment {} ({} bytes)",
+            record.source, record.attachment_guid, record.size_bytes
+        );
+
+        self.evict().await?;
+        Ok(record)
+    }
+
+    /// Removes least-recently-used entries until the cache fits its size limit.
+    async fn evict(&self) -> Result<()> {
+        let mut total = self.database.cached_attachment_bytes().await?;
+
+        while total > self.max_bytes {
+            let batch = self.database.get_least_recent_attachments(EVICTION_BATCH).await?;
+            if batch.is_empty() {
+                break;
+            }
+
+            for record in batch {
+                if total <= self.max_bytes {
+                    break;
+                }
+
+                let remaining = self
+                    .database
+                    .delete_cached_attachment(&record.attachment_guid)
+                    .await?;
+
+                // Only remove the file once nothing else points at the same content
+                if remaining == 0 {
+                    tokio::fs::remove_file(self.path_for(&record.content_hash))
+                        .await
+                        .ok();
+                    total -= record.size_bytes;
+                }
+
+                info!("Evicted attachment {} from cache", record.attachment_guid);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn path_for(&self, content_hash: &str) -> PathBuf {
+        self.dir.join(content_hash)
+    }
+}
+
+fn content_hash(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn test_cache(max_bytes: i64) -> (AttachmentCache, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("attachment-cache-{}", uuid::Uuid::new_v4()));
+        let database = Database::new(&format!("sqlite:{}", dir.join("bot.db").display()))
+            .await
+            .unwrap();
+        let cache = AttachmentCache::new(dir.join("files"), max_bytes, database).unwrap();
+        (cache, dir)
+    }
+
+    fn attachment(guid: &str) -> BlueBubblesAttachment {
+        serde_json::from_value(serde_json::json!({ "guid": guid, "mimeType": "image/png" })).unwrap()
+    }
+
+    async fn put(cache: &AttachmentCache, guid: &str, data: &[u8]) {
+        cache
+            .put("chat", &attachment(guid), data, AttachmentSource::Downloaded, None)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_get_hits_and_misses() {
+        let (cache, dir) = test_cache(1024).await;
+
+        put(&cache, "a", b"hello").await;
+        let (record, data) = cache.get("a").await.unwrap().unwrap();
+        assert_eq!(data, b"hello");
+        assert_eq!(record.content_hash, content_hash(b"hello"));
+        assert!(cache.get("missing").await.unwrap().is_none());
+
+        // A file deleted behind the cache's back is a miss, not an error
+        std::fs::remove_file(cache.path_for(&record.content_hash)).unwrap();
+        assert!(cache.get("a").await.unwrap().is_none());
+
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    #[tokio::test]
+    async fn test_evicts_least_recently_used_first() {
+        let (cache, dir) = test_cache(10).await;
+
+        put(&cache, "a", b"aaaa").await;
+        put(&cache, "b", b"bbbb").await;
+        // Reading "a" makes "b" the oldest
+        assert!(cache.get("a").await.unwrap().is_some());
+        put(&cache, "c", b"cccc").await;
+
+        assert!(cache.get("b").await.unwrap().is_none());
+        assert!(cache.get("a").await.unwrap().is_some());
+        assert!(cache.get("c").await.unwrap().is_some());
+        assert_eq!(cache.database.cached_attachment_bytes().await.unwrap(), 8);
+
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    #[tokio::test]
+    async fn test_eviction_continues_past_one_batch() {
+        let (cache, dir) = test_cache(1024).await;
+        for i in 0..(EVICTION_BATCH + 10) {
+            put(&cache, &format!("guid-{}", i), &i.to_le_bytes()).await;
+        }
+
+        let small = AttachmentCache { max_bytes: 16, ..cache.clone() };
+        small.evict().await.unwrap();
+
+        assert_eq!(small.database.cached_attachment_bytes().await.unwrap(), 16);
+        let newest = format!("guid-{}", EVICTION_BATCH + 9);
+        assert!(small.get(&newest).await.unwrap().is_some());
+
+        std::fs::remove_dir_all(dir).ok();
+    }
+}
@@ -3,6 +3,7 @@ use reqwest::{Client, multipart};
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
 use tracing::{debug, error, info};
+use crate::media::OutgoingFile;
 use crate::types::{BlueBubblesChat, BlueBubblesMessage, BlueBubblesAttachment};
 
 #[derive(Debug, Clone, Serialize)]
@@ -42,6 +43,7 @@ struct ApiResponse<T> {
     error: Option<String>,
 }
 
+#[derive(Clone)]
 pub struct BlueBubblesClient {
     client: Client,
     base_url: String,
@@ -71,6 +73,20 @@ impl BlueBubblesClient {
         format!("{}/api/v1{}{}", self.base_url, endpoint, password_param)
     }
 
+    /// Checks the BlueBubbles server is up and accepts our password.
+    pub async fn ping(&self) -> Result<()> {
+        let response = self.client
+            .get(self.build_url("/ping"))
+            .send()
+            .await
+            .context("BlueBubbles is unreachable")?;
+
+        if !response.status().is_success() {
+            return Err(anyhow::anyhow!("BlueBubbles ping failed with status: {}", response.status()));
+        }
+        Ok(())
+    }
+
     pub async fn get_chats(&self) -> Result<Vec<BlueBubblesChat>> {
         let url = self.build_url("/chat/query");
         
@@ -107,16 +123,12 @@ impl BlueBubblesClient {
                 Ok(chats)
             }
             None => {
-                error!("No chat data in response: {:?}", api_response.error);
+                error!("No chat data in response: {:?} ({:?})", api_response.error, api_response.message);
                 Ok(vec![])
             }
         }
     }
 
-    pub async fn get_messages(&self, chat_guid: &str) -> Result<Vec<BlueBubblesMessage>> {
-        self.get_messages_after(chat_guid, None).await
-    }
-
     pub async fn get_messages_after(&self, chat_guid: &str, after_timestamp: Option<u64>) -> Result<Vec<BlueBubblesMessage>> {
         let url = self.build_url("/message/query");
         
@@ -126,7 +138,7 @@ impl BlueBubblesClient {
             offset: 0,
             sort: "DESC".to_string(),
             after: after_timestamp,
-            with: vec!["attachment".to_string()],
+            with: vec!["attachment".to_string(), "handle".to_string()],
         };
 
         match after_timestamp {
@@ -158,7 +170,7 @@ impl BlueBubblesClient {
                 Ok(messages)
             }
             None => {
-                error!("No message data in response: {:?}", api_response.error);
+                error!("No message data in response: {:?} ({:?})", api_response.error, api_response.message);
                 Ok(vec![])
             }
         }
@@ -197,7 +209,7 @@ impl BlueBubblesClient {
         Ok(())
     }
 
-    pub async fn send_attachment(&self, chat_guid: &str, image_data: Vec<u8>, filename: &str) -> Result<()> {
+    pub async fn send_attachment(&self, chat_guid: &str, file: OutgoingFile) -> Result<()> {
         let url = self.build_url("/message/attachment");
         
         let temp_guid = format!("temp-{}-{}", 
@@ -208,12 +220,12 @@ impl BlueBubblesClient {
         let form = multipart::Form::new()
             .text("chatGuid", chat_guid.to_string())
             .text("tempGuid", temp_guid)
-            .text("name", filename.to_string())
-            .part("attachment", multipart::Part::bytes(image_data)
-                .file_name(filename.to_string())
-                .mime_str("image/png")?);
+            .text("name", file.filename.clone())
+            .part("attachment", multipart::Part::bytes(file.data)
+                .file_name(file.filename.clone())
+                .mime_str(&file.mime_type)?);
 
-        debug!("Sending attachment to chat {}: {}", chat_guid, filename);
+        debug!("Sending attachment to chat {}: {} ({})", chat_guid, file.filename, file.mime_type);
 
         let response = self.client
             .post(&url)
@@ -264,6 +276,52 @@ impl BlueBubblesClient {
         Ok(attachment_bytes.to_vec())
     }
 
+    pub async fn get_message(&self, message_guid: &str) -> Result<Option<BlueBubblesMessage>> {
+        let url = self.build_url(&format!("/message/{}", message_guid));
+        let separator = if url.contains('?') { '&' } else { '?' };
+        let url = format!("{}{}with=attachment", url, separator);
+
+        debug!("Fetching message {}", message_guid);
+
+        let response = self.client
+            .get(&url)
+            .send()
+            .await
+            .context("Failed to send message lookup request")?;
+
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+
+        if !response.status().is_success() {
+            error!("Message lookup failed with status: {}", response.status());
+            let text = response.text().await.unwrap_or_default();
+            return Err(anyhow::anyhow!("Message lookup failed: {}", text));
+        }
+
+        let api_response: ApiResponse<BlueBubblesMessage> = response
+            .json()
+            .await
+            .context("Failed to parse message lookup response")?;
+
+        Ok(api_response.data)
+    }
+
+    pub fn is_audio_attachment(&self, attachment: &BlueBubblesAttachment) -> bool {
+        if let Some(mime_type) = &attachment.mime_type {
+            mime_type.starts_with("audio/")
+        } else if let Some(name) = &attachment.transfer_name {
+            let name_lower = name.to_lowercase();
+            name_lower.ends_with(".caf") ||
+            name_lower.ends_with(".m4a") ||
+            name_lower.ends_with(".mp3") ||
+            name_lower.ends_with(".wav") ||
+            name_lower.ends_with(".amr")
+        } else {
+            false
+        }
+    }
+
     pub fn is_image_attachment(&self, attachment: &BlueBubblesAttachment) -> bool {
         if let Some(mime_type) = &attachment.mime_type {
             mime_type.starts_with("image/")
@@ -1,21 +1,34 @@
 use anyhow::Result;
 use chrono::Utc;
-use std::collections::VecDeque;
+use chrono_tz::Tz;
+use std::collections::{HashMap, VecDeque};
+use std::time::Instant;
 use tokio::sync::mpsc;
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn};
 
 use crate::{
-    ai_clients::AIClients,
+    ai_clients::{AIClients, CompletionOptions},
+    attachment_cache::{AttachmentCache, AttachmentSource},
     bluebubbles::BlueBubblesClient,
-    commands::CommandHandler,
-    config::Config,
+    commands::{CommandHandler, ContextChange},
+    config::{Config, IntentClassifier},
     database::Database,
-    types::{ChatConfig, Message, MessageRole, QueuedMessage},
+    media::{check_attachment_size, safe_filename, AudioPipeline, ImagePipeline, OutgoingFile, VisionImage},
+    polls::{format_poll, validate_poll},
+    scheduling::{describe_now, format_local, parse_local_datetime, resolve_timezone},
+    triggers::{custom_triggers, TriggerSet},
+    usage::UsageRecorder,
+    types::{AmbientMessage, BlueBubblesAttachment, ChatConfig, Message, MessageRole, QueuedMessage, RecurringJob, ReplyMode},
 };
 
+/// What the model says in proactive mode when it has nothing to add.
+const PASS_MARKER: &str = "[PASS]";
+
 #[derive(Debug, Clone)]
 pub enum ChatAgentMessage {
     ProcessMessage(QueuedMessage),
+    Ambient(Vec<AmbientMessage>),
+    RunPrompt(RecurringJob),
     Shutdown,
 }
 
@@ -27,6 +40,18 @@ pub struct ChatAgent {
     bluebubbles: BlueBubblesClient,
     database: Database,
     command_handler: CommandHandler,
+    image_pipeline: ImagePipeline,
+    audio_pipeline: AudioPipeline,
+    attachment_cache: AttachmentCache,
+    max_attachment_bytes: i64,
+    max_vision_images: usize,
+    vision_context_images: bool,
+    bot_trigger: String,
+    intent_classifier: IntentClassifier,
+    intent_model: Option<String>,
+    default_timezone: String,
+    poll_timeout_hours: i64,
+    usage: UsageRecorder,
     receiver: mpsc::Receiver<ChatAgentMessage>,
 }
 
@@ -38,38 +63,40 @@ impl ChatAgent {
         receiver: mpsc::Receiver<ChatAgentMessage>,
     ) -> Result<Self> {
         // Load chat-specific config from database or create default
-        let config = database
+        let mut config = database
             .get_chat_config(&chat_guid)
             .await?
-            .unwrap_or_else(|| ChatConfig {
-                chat_guid: chat_guid.clone(),
-                character_prompt: None,
-                triggers: global_config.triggers(),
-                trigger_name: "myai".to_string(),
-                use_ollama: false,
-                created_at: Utc::now(),
-                updated_at: Utc::now(),
-            });
+            .unwrap_or_else(|| ChatConfig::new(chat_guid.clone()));
+        config.triggers = custom_triggers(&config.triggers, &global_config.legacy_triggers());
 
         // Load recent messages from database to populate context
-        let recent_messages = database.get_recent_messages(&chat_guid, 10).await?;
+        let recent_messages = database
+            .get_recent_messages(&chat_guid, config.context_size as i64)
+            .await?;
         let mut context = VecDeque::new();
         for message in recent_messages {
             context.push_back(message);
         }
 
-        let ai_clients = AIClients::new(
-            global_config.openai_api_key.clone(),
-            global_config.ollama_api.clone(),
-            global_config.ollama_model.clone(),
-        );
+        let ai_clients = AIClients::new(global_config);
 
         let bluebubbles = BlueBubblesClient::new(
             global_config.bluebubbles_api.clone(),
             global_config.bluebubbles_password.clone(),
         );
 
-        let command_handler = CommandHandler::new(ai_clients.clone(), database.clone())?;
+        let usage = UsageRecorder::from_config(global_config, database.clone());
+        let command_handler = CommandHandler::new(
+            ai_clients.clone(),
+            database.clone(),
+            global_config.admin_handles.clone(),
+            global_config.default_timezone.clone(),
+            global_config.poll_timeout_hours,
+            usage.clone(),
+        )?;
+        let image_pipeline = ImagePipeline::from_config(global_config);
+        let audio_pipeline = AudioPipeline::from_config(global_config);
+        let attachment_cache = AttachmentCache::from_config(global_config, database.clone())?;
 
         Ok(Self {
             chat_guid,
@@ -79,6 +106,18 @@ impl ChatAgent {
             bluebubbles,
             database,
             command_handler,
+            image_pipeline,
+            audio_pipeline,
+            attachment_cache,
+            max_attachment_bytes: global_config.max_attachment_bytes,
+            max_vision_images: global_config.max_vision_images,
+            vision_context_images: global_config.vision_context_images,
+            bot_trigger: global_config.bot_trigger.clone(),
+            intent_classifier: global_config.intent_classifier,
+            intent_model: global_config.intent_model.clone(),
+            default_timezone: global_config.default_timezone.clone(),
+            poll_timeout_hours: global_config.poll_timeout_hours,
+            usage,
             receiver,
         })
     }
@@ -105,6 +144,17 @@ impl ChatAgent {
                         }
                     }
                 }
+                ChatAgentMessage::Ambient(messages) => {
+                    // Failures stay quiet: nobody asked for this reply
+                    if let Err(e) = self.consider_chiming_in(messages).await {
+                        error!("Error in proactive check for chat {}: {}", self.chat_guid, e);
+                    }
+                }
+                ChatAgentMessage::RunPrompt(job) => {
+                    if let Err(e) = self.run_scheduled_prompt(&job).await {
+                        error!("Error running scheduled prompt in chat {}: {}", self.chat_guid, e);
+                    }
+                }
                 ChatAgentMessage::Shutdown => {
                     info!("Shutting down chat agent for chat: {}", self.chat_guid);
                     break;
@@ -115,41 +165,118 @@ impl ChatAgent {
         Ok(())
     }
 
+    /// Asks the intent classifier whether a mid-sentence mention is meant
+    /// for the bot.
+    async fn mention_addresses_bot(&self, text: &str, sender: Option<&str>) -> bool {
+        let use_ollama = self.intent_classifier == IntentClassifier::Ollama;
+        match self
+            .ai_clients
+            .classify_intent(
+                text,
+                &self.config.trigger_name,
+                use_ollama,
+                self.intent_model.as_deref(),
+            )
+            .await
+        {
+            Ok((confidence, completion)) => {
+                self.usage
+                    .record_completion(&self.chat_guid, sender, "intent", &completion)
+                    .await;
+                debug!(
+                    "Intent classifier scored '{}' at {} (threshold {})",
+                    text, confidence, self.config.intent_threshold
+                );
+                confidence >= self.config.intent_threshold
+            }
+            Err(e) => {
+                // Fall back to answering, as if there were no classifier
+                warn!("Intent classification failed, treating mention as a trigger: {}", e);
+                true
+            }
+        }
+    }
+
     async fn handle_message(&mut self, queued_message: QueuedMessage) -> Result<()> {
         let text = &queued_message.text;
         debug!("Processing message in chat {}: {}", self.chat_guid, text);
 
-        // Try to handle as a command first
-        if let Some(response) = self
+        // Drop the trigger words so "myai @reset" runs the command and the
+        // model sees just the request
+        let request = TriggerSet::new(std::slice::from_ref(&self.bot_trigger), &self.config).strip(text);
+
+        // Try to handle as a command first. The original text goes first, so
+        // arguments that happen to be trigger words ("@name myai") survive.
+        let sender = queued_message.sender.as_deref();
+        let mut response = self
             .command_handler
-            .handle_command(&self.chat_guid, text, &mut self.config)
-            .await?
-        {
-            // It was a command, send the response and clear context if needed
+            .handle_command(&self.chat_guid, sender, text, &mut self.config)
+            .await?;
+        if response.is_none() && request != *text {
+            response = self
+                .command_handler
+                .handle_command(&self.chat_guid, sender, &request, &mut self.config)
+                .await?;
+        }
+        if let Some(response) = response {
+            // It was a command, keep the in-memory context in line with the database
+            match response.context {
+                ContextChange::Keep => {}
+                ContextChange::Clear => self.context.clear(),
+                ContextChange::ForgetLast(count) => {
+                    let keep = self.context.len().saturating_sub(count);
+                    self.context.truncate(keep);
+                }
+            }
+
             self.bluebubbles
-                .send_message(&self.chat_guid, &response)
+                .send_message(&self.chat_guid, &response.reply)
                 .await?;
 
-            // If it was a character command, clear the context
-            if text.to_lowercase().starts_with("@character") {
-                self.context.clear();
+            return Ok(());
+        }
+
+        // Paused chats only answer commands
+        if self.config.paused {
+            debug!("Chat {} is paused, ignoring message", self.chat_guid);
+            return Ok(());
+        }
+
+        if queued_message.mention {
+            if self.mention_addresses_bot(text, sender).await {
+                // Queue it again as a plain trigger so it goes through the
+                // rate limits and quotas before it's answered
+                self.database
+                    .queue_message(&self.chat_guid, sender, text, &queued_message.attachments, false)
+                    .await?;
+            } else {
+                debug!("Mention in chat {} isn't meant for the bot, ignoring it", self.chat_guid);
             }
+            return Ok(());
+        }
 
+        let content = self
+            .with_transcripts(&request, &queued_message.attachments)
+            .await;
+        if content.is_empty() {
+            self.bluebubbles
+                .send_message(&self.chat_guid, "❌ Couldn't make out that voice memo. Please try again.")
+                .await?;
             return Ok(());
         }
 
-        // Not a command, process as regular message
         let user_message = Message {
             role: MessageRole::User,
-            content: text.clone(),
+            content,
             timestamp: queued_message.timestamp,
+            attachments: queued_message.attachments.clone(),
         };
 
         // Add to context
         self.context.push_back(user_message.clone());
 
-        // Keep only last 10 messages
-        while self.context.len() > 10 {
+        // Keep only the most recent messages
+        while self.context.len() > self.config.context_size {
             self.context.pop_front();
         }
 
@@ -161,88 +288,241 @@ impl ChatAgent {
             .save_message(&self.chat_guid, &user_message)
             .await?;
 
-        // Generate AI response
-        let system_prompt = self.config.character_prompt
-            .as_ref()
-            .map(|s| s.as_str())
-            .unwrap_or("You are MyAI, a casual assistant in a private friend group chat. Be brief and natural unless asked to elaborate. Match the group's tone and energy.");
+        // Generate AI response. The current time lets the model turn "tomorrow at 9" into a date.
+        let system_prompt = format!(
+            "{}\n\nCurrent time: {}",
+            self.system_prompt(),
+            describe_now(self.timezone())
+        );
 
         let context_messages: Vec<_> = self.context.iter().cloned().collect();
 
-        // Check for recent image from same user
-        let image_data = self.get_recent_user_image(&self.chat_guid).await;
+        // Load images shared with this message (and earlier ones if enabled)
+        let images = self.load_images(&context_messages).await;
 
         let ai_response = self
-            .ai_clients
-            .generate_chat_completion(
+            .complete(
                 &context_messages,
-                system_prompt,
-                self.config.use_ollama,
+                &system_prompt,
                 true,
-                image_data,
+                &images,
+                queued_message.sender.as_deref(),
             )
             .await?;
 
-        // Check if AI wants to generate an image
-        if ai_response.starts_with("[TOOL_CALL:request_picture:") {
-            let end_idx = ai_response.find(']').unwrap_or(ai_response.len());
-            let args_json = &ai_response[27..end_idx]; // Skip "[TOOL_CALL:request_picture:"
-
-            if let Ok(args) = serde_json::from_str::<serde_json::Value>(args_json) {
-                if let Some(description) = args.get("description").and_then(|v| v.as_str()) {
-                    match self.generate_and_send_image(description).await {
-                        Ok(_) => {
-                            let response_text = "✅ Generated and sent a picture!";
-                            self.bluebubbles
-                                .send_message(&self.chat_guid, response_text)
-                                .await?;
-
-                            let assistant_message = Message {
-                                role: MessageRole::Assistant,
-                                content: response_text.to_string(),
-                                timestamp: Utc::now(),
-                            };
-                            self.context.push_back(assistant_message.clone());
-                            self.database
-                                .save_message(&self.chat_guid, &assistant_message)
-                                .await?;
-                            return Ok(());
-                        }
-                        Err(e) => {
-                            error!("Failed to generate image: {}", e);
-                            let error_text = "❌ Failed to generate image. Please try again.";
-                            self.bluebubbles
-                                .send_message(&self.chat_guid, error_text)
-                                .await?;
-
-                            let assistant_message = Message {
-                                role: MessageRole::Assistant,
-                                content: error_text.to_string(),
-                                timestamp: Utc::now(),
-                            };
-                            self.context.push_back(assistant_message.clone());
-                            self.database
-                                .save_message(&self.chat_guid, &assistant_message)
-                                .await?;
-                            return Ok(());
-                        }
+        // Check if AI wants to use a tool
+        match parse_tool_call(&ai_response) {
+            Some((tool_name, args)) => {
+                let sender = queued_message.sender.as_deref();
+                let (response_text, attachments) = match self.run_tool(&tool_name, &args, sender).await {
+                    Ok(result) => result,
+                    Err(e) => {
+                        error!("Tool {} failed in chat {}: {}", tool_name, self.chat_guid, e);
+                        (tool_error_text(&tool_name).to_string(), Vec::new())
                     }
-                }
+                };
+                self.send_assistant_reply(response_text, attachments).await?;
             }
+            None => self.send_reply(ai_response).await?,
+        }
+
+        debug!("Successfully processed message in chat {}", self.chat_guid);
+        Ok(())
+    }
+
+    /// Sends the model's reply, spoken if the chat has voice replies on.
+    async fn send_reply(&mut self, response_text: String) -> Result<()> {
+        if self.config.reply_mode == ReplyMode::Text {
+            return self.send_assistant_reply(response_text, Vec::new()).await;
         }
 
-        // Regular text response
-        let response_text = ai_response;
+        let voice_attachment = match self
+            .ai_clients
+            .synthesize_speech(&response_text, self.config.voice.as_deref())
+            .await
+        {
+            Ok(audio) => {
+                self.send_file(OutgoingFile::with_stem(audio, "voice-reply"), None)
+                    .await
+            }
+            Err(e) => Err(e),
+        };
+
+        match voice_attachment {
+            Ok(attachment) if self.config.reply_mode == ReplyMode::Voice => {
+                // Voice only: keep the words in context without sending them as text
+                self.record_assistant_message(response_text, vec![attachment])
+                    .await
+            }
+            Ok(attachment) => {
+                self.send_assistant_reply(response_text, vec![attachment])
+                    .await
+            }
+            Err(e) => {
+                error!("Voice reply failed in chat {}, sending text: {}", self.chat_guid, e);
+                self.send_assistant_reply(response_text, Vec::new()).await
+            }
+        }
+    }
 
+    async fn send_assistant_reply(
+        &mut self,
+        response_text: String,
+        attachments: Vec<BlueBubblesAttachment>,
+    ) -> Result<()> {
         self.bluebubbles
             .send_message(&self.chat_guid, &response_text)
             .await?;
 
+        self.record_assistant_message(response_text, attachments)
+            .await
+    }
+
+    fn system_prompt(&self) -> &str {
+        self.config.character_prompt
+            .as_deref()
+            .unwrap_or("You are MyAI, a casual assistant in a private friend group chat. Be brief and natural unless asked to elaborate. Match the group's tone and energy.")
+    }
+
+    fn timezone(&self) -> Tz {
+        resolve_timezone(self.config.timezone.as_deref(), &self.default_timezone)
+    }
+
+    /// Proactive mode: shows the model what the chat has been saying without
+    /// the bot and lets it decide whether to join in.
+    async fn consider_chiming_in(&mut self, messages: Vec<AmbientMessage>) -> Result<()> {
+        if !self.config.proactive || self.config.paused || messages.is_empty() {
+            return Ok(());
+        }
+
+        let transcript: Vec<String> = messages
+            .iter()
+            .map(|message| {
+                format!(
+                    "{}: {}",
+                    message.sender.as_deref().unwrap_or("Someone"),
+                    message.text
+                )
+            })
+            .collect();
+        let ambient_message = Message {
+            role: MessageRole::User,
+            content: format!(
+                "[Recent group messages, not addressed to you]\n{}",
+                transcript.join("\n")
+            ),
+            timestamp: Utc::now(),
+            attachments: Vec::new(),
+        };
+
+        let system_prompt = format!(
+            "{}\n\nNobody asked you anything. Only reply if you have something genuinely useful or fun to add to the conversation above. Otherwise reply with exactly {}.",
+            self.system_prompt(),
+            PASS_MARKER
+        );
+        let mut context_messages: Vec<_> = self.context.iter().cloned().collect();
+        context_messages.push(ambient_message.clone());
+
+        let ai_response = self
+            .complete(&context_messages, &system_prompt, false, &HashMap::new(), None)
+            .await?;
+
+        let reply = ai_response.trim();
+        if reply.is_empty() || reply.contains(PASS_MARKER) {
+            debug!("Model passed on chiming in to chat {}", self.chat_guid);
+            return Ok(());
+        }
+
+        info!("Chiming in to chat {} unprompted", self.chat_guid);
+
+        // Keep what was overheard so the reply makes sense in later turns
+        self.database.save_chat_config(&self.config).await?;
+        self.context.push_back(ambient_message.clone());
+        self.database
+            .save_message(&self.chat_guid, &ambient_message)
+            .await?;
+        self.send_reply(reply.to_string()).await
+    }
+
+    /// Runs a recurring job's prompt and posts the answer to the chat.
+    async fn run_scheduled_prompt(&mut self, job: &RecurringJob) -> Result<()> {
+        if self.config.paused {
+            debug!("Skipping scheduled prompt in paused chat {}", self.chat_guid);
+            return Ok(());
+        }
+
+        let request = Message {
+            role: MessageRole::User,
+            content: format!("[Scheduled prompt] {}", job.prompt),
+            timestamp: Utc::now(),
+            attachments: Vec::new(),
+        };
+        let system_prompt = format!(
+            "{}\n\nCurrent time: {}\n\nThis is a scheduled post that nobody just asked for. Write it for the whole chat and send only the post itself.",
+            self.system_prompt(),
+            describe_now(self.timezone())
+        );
+        // What the chat talked about since the job last ran, so a daily
+        // summary covers that day
+        let limit = self.config.context_size as i64;
+        let mut context_messages = match job.last_run_at {
+            Some(since) => self.database.get_messages_since(&self.chat_guid, since, limit).await?,
+            None => self.database.get_recent_messages(&self.chat_guid, limit).await?,
+        };
+        context_messages.push(request.clone());
+
+        let ai_response = self
+            .complete(&context_messages, &system_prompt, false, &HashMap::new(), None)
+            .await?;
+
+        info!("Posting scheduled prompt to chat {}", self.chat_guid);
+        self.database.save_chat_config(&self.config).await?;
+        self.context.push_back(request.clone());
+        while self.context.len() > self.config.context_size {
+            self.context.pop_front();
+        }
+        self.database.save_message(&self.chat_guid, &request).await?;
+        self.send_reply(ai_response.trim().to_string()).await
+    }
+
+    /// Asks the chat's model for a reply and records the tokens it used
+    /// against the chat and the sender who asked.
+    async fn complete(
+        &self,
+        messages: &[Message],
+        system_prompt: &str,
+        include_tools: bool,
+        images: &HashMap<String, VisionImage>,
+        sender: Option<&str>,
+    ) -> Result<String> {
+        let completion = self
+            .ai_clients
+            .generate_chat_completion(
+                messages,
+                system_prompt,
+                &CompletionOptions::from_chat_config(&self.config),
+                include_tools,
+                images,
+            )
+            .await?;
+
+        self.usage
+            .record_completion(&self.chat_guid, sender, "chat", &completion)
+            .await;
+        Ok(completion.text)
+    }
+
+    async fn record_assistant_message(
+        &mut self,
+        response_text: String,
+        attachments: Vec<BlueBubblesAttachment>,
+    ) -> Result<()> {
         // Add assistant response to context
         let assistant_message = Message {
             role: MessageRole::Assistant,
-            content: response_text.clone(),
+            content: response_text,
             timestamp: Utc::now(),
+            attachments,
         };
 
         self.context.push_back(assistant_message.clone());
@@ -250,93 +530,407 @@ impl ChatAgent {
             .save_message(&self.chat_guid, &assistant_message)
             .await?;
 
-        debug!("Successfully processed message in chat {}", self.chat_guid);
         Ok(())
     }
 
-    async fn generate_and_send_image(&self, description: &str) -> Result<()> {
+    /// Runs a tool the model asked for, returning the reply text and any
+    /// attachments it sent.
+    async fn run_tool(
+        &self,
+        tool_name: &str,
+        args: &serde_json::Value,
+        sender: Option<&str>,
+    ) -> Result<(String, Vec<BlueBubblesAttachment>)> {
+        match tool_name {
+            "request_picture" => {
+                let description = string_arg(args, "description")?;
+                let attachment = self.generate_and_send_image(description, sender).await?;
+                Ok(("✅ Generated and sent a picture!".to_string(), vec![attachment]))
+            }
+            "edit_image" => {
+                let instruction = string_arg(args, "instruction")?;
+                let count = args.get("count").and_then(|v| v.as_u64()).unwrap_or(1).clamp(1, 4);
+                let attachments = self.edit_and_send_image(instruction, count as u32, sender).await?;
+                let reply = if attachments.len() > 1 {
+                    format!("✅ Sent {} versions of your photo!", attachments.len())
+                } else {
+                    "✅ Edited and sent your photo!".to_string()
+                };
+                Ok((reply, attachments))
+            }
+            "send_file" => {
+                let filename = string_arg(args, "filename")?;
+                let content = string_arg(args, "content")?.as_bytes().to_vec();
+                let file = match safe_filename(filename) {
+                    Some(filename) => OutgoingFile::new(content, filename),
+                    None => OutgoingFile::with_stem(content, &format!("file-{}", Utc::now().format("%Y%m%d-%H%M%S"))),
+                };
+                let reply = format!("📎 Sent {}", file.filename);
+                let attachment = self.send_file(file, None).await?;
+                Ok((reply, vec![attachment]))
+            }
+            "resend_image" => {
+                let index = args.get("index").and_then(|v| v.as_u64()).unwrap_or(1).max(1);
+                let attachment = self.resend_generated_image(index as i64).await?;
+                Ok(("✅ Sent it again!".to_string(), vec![attachment]))
+            }
+            "set_reminder" => {
+                let time = string_arg(args, "time")?;
+                let message = string_arg(args, "message")?.trim();
+                if message.is_empty() {
+                    return Err(anyhow::anyhow!("Reminder message can't be empty"));
+                }
+                let tz = self.timezone();
+                let send_at = parse_local_datetime(time, tz)
+                    .ok_or_else(|| anyhow::anyhow!("Unreadable reminder time: {}", time))?;
+                if send_at <= Utc::now() {
+                    return Err(anyhow::anyhow!("Reminder time {} is in the past", time));
+                }
+
+                let id = self
+                    .database
+                    .schedule_message(&self.chat_guid, message, send_at)
+                    .await?;
+                info!("Scheduled reminder #{} for chat {} at {}", id, self.chat_guid, send_at);
+                Ok((
+                    format!("✅ Reminder #{} set for {}: {}", id, format_local(send_at, tz), message),
+                    Vec::new(),
+                ))
+            }
+            "create_poll" => {
+                let question = string_arg(args, "question")?.trim();
+                let options: Vec<String> = args
+                    .get("options")
+                    .and_then(|v| v.as_array())
+                    .map(|options| {
+                        options
+                            .iter()
+                            .filter_map(|option| option.as_str())
+                            .map(|option| option.trim().to_string())
+                            .filter(|option| !option.is_empty())
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                validate_poll(question, &options).map_err(|e| anyhow::anyhow!(e))?;
+
+                let hours = args
+                    .get("hours")
+                    .and_then(|v| v.as_i64())
+                    .unwrap_or(self.poll_timeout_hours)
+                    .clamp(1, 24 * 7);
+                let closes_at = Utc::now() + chrono::Duration::hours(hours);
+                let poll = self
+                    .database
+                    .create_poll(&self.chat_guid, question, &options, closes_at)
+                    .await?;
+                info!("Created poll #{} in chat {}", poll.id, self.chat_guid);
+                Ok((format_poll(&poll), Vec::new()))
+            }
+            _ => Err(anyhow::anyhow!("Unknown tool: {}", tool_name)),
+        }
+    }
+
+    async fn generate_and_send_image(
+        &self,
+        description: &str,
+        sender: Option<&str>,
+    ) -> Result<BlueBubblesAttachment> {
         info!(
             "Generating image for chat {}: {}",
             self.chat_guid, description
         );
 
         // Generate the image
+        let started = Instant::now();
         let image_data = self.ai_clients.generate_image(description).await?;
-
-        // Send the image to the chat
-        self.bluebubbles
-            .send_attachment(&self.chat_guid, image_data, "generated-image.png")
-            .await?;
+        self.usage
+            .record_images(&self.chat_guid, sender, 1, started.elapsed().as_millis() as i64)
+            .await;
+        let attachment = self.send_generated_image(image_data, description).await?;
 
         info!(
             "Successfully generated and sent image to chat {}",
             self.chat_guid
         );
-        Ok(())
+        Ok(attachment)
     }
 
-    async fn get_recent_user_image(&self, chat_guid: &str) -> Option<Vec<u8>> {
-        // Look for an image in the most recent message from a user (not from us)
-        // Only check the very last user message to avoid analyzing old images
-
-        info!("Looking for image in most recent user message in chat {}", chat_guid);
-
-        // Use BlueBubbles to get recent messages with attachments (limit to just a few)
-        match self.bluebubbles.get_messages_after(chat_guid, None).await {
-            Ok(messages) => {
-                info!("Found {} messages to check for images", messages.len());
-                
-                // Find the most recent message from a user (not the bot)
-                for (i, message) in messages.iter().enumerate() {
-                    info!("Message {}: guid={}, is_from_me={:?}, has_attachments={}", 
-                          i, message.guid, message.is_from_me, message.attachments.is_some());
-                    
-                    // Skip messages from the bot
-                    if message.is_from_me == Some(true) {
-                        continue;
-                    }
+    /// Edits the most recent photo shared in the chat and sends the results.
+    async fn edit_and_send_image(
+        &self,
+        instruction: &str,
+        count: u32,
+        sender: Option<&str>,
+    ) -> Result<Vec<BlueBubblesAttachment>> {
+        let image = self
+            .get_recent_user_image()
+            .await
+            .ok_or_else(|| anyhow::anyhow!("No recent photo to edit in chat {}", self.chat_guid))?;
+        let image = self.image_pipeline.prepare_for_edit(image).await?;
 
-                    // This is the most recent user message - check if it has an image
-                    if let Some(attachments) = &message.attachments {
-                        for attachment in attachments {
-                            if self.bluebubbles.is_image_attachment(attachment) {
-                                info!("Found image attachment in most recent user message: {:?}", attachment.guid);
-
-                                // Download the image
-                                match self.bluebubbles.download_attachment(attachment).await {
-                                    Ok(image_data) => {
-                                        info!("Successfully downloaded image for vision processing ({} bytes)", image_data.len());
-                                        return Some(image_data);
-                                    }
-                                    Err(e) => {
-                                        error!(
-                                            "Failed to download attachment {}: {}",
-                                            attachment.guid, e
-                                        );
-                                        continue;
-                                    }
-                                }
-                            }
-                        }
+        info!(
+            "Editing image for chat {} ({} result(s)): {}",
+            self.chat_guid, count, instruction
+        );
+
+        let started = Instant::now();
+        let images = self.ai_clients.edit_image(&image, instruction, count).await?;
+        self.usage
+            .record_images(&self.chat_guid, sender, images.len(), started.elapsed().as_millis() as i64)
+            .await;
+
+        let mut attachments = Vec::new();
+        for image_data in images {
+            attachments.push(self.send_generated_image(image_data, instruction).await?);
+        }
+        Ok(attachments)
+    }
+
+    /// Caches a generated image and sends it to the chat.
+    async fn send_generated_image(
+        &self,
+        image_data: Vec<u8>,
+        description: &str,
+    ) -> Result<BlueBubblesAttachment> {
+        self.send_file(OutgoingFile::with_stem(image_data, "generated-image"), Some(description))
+            .await
+    }
+
+    /// Sends a file the bot produced and returns a reference to it for the
+    /// conversation context. A copy is kept in the attachment cache so it can
+    /// be resent or looked at again later.
+    async fn send_file(
+        &self,
+        file: OutgoingFile,
+        description: Option<&str>,
+    ) -> Result<BlueBubblesAttachment> {
+        let attachment = BlueBubblesAttachment {
+            guid: format!("generated-{}", uuid::Uuid::new_v4()),
+            original_rowid: None,
+            mime_type: Some(file.mime_type.clone()),
+            transfer_name: Some(file.filename.clone()),
+            total_bytes: Some(file.data.len() as i64),
+        };
+
+        if let Err(e) = self
+            .attachment_cache
+            .put(
+                &self.chat_guid,
+                &attachment,
+                &file.data,
+                AttachmentSource::Generated,
+                description,
+            )
+            .await
+        {
+            error!("Failed to cache generated file: {}", e);
+        }
+
+        self.bluebubbles
+            .send_attachment(&self.chat_guid, file)
+            .await?;
+
+        Ok(attachment)
+    }
+
+    /// Resends a previously generated image; `index` 1 is the most recent.
+    async fn resend_generated_image(&self, index: i64) -> Result<BlueBubblesAttachment> {
+        let record = self
+            .database
+            .get_generated_images(&self.chat_guid, index)
+            .await?
+            .into_iter()
+            .nth(index as usize - 1)
+            .ok_or_else(|| anyhow::anyhow!("No generated image #{} in this chat", index))?;
+
+        let (record, image_data) = self
+            .attachment_cache
+            .get(&record.attachment_guid)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Generated image {} is no longer cached", record.attachment_guid))?;
+
+        let file = match &record.filename {
+            Some(filename) => OutgoingFile::new(image_data, filename.clone()),
+            None => OutgoingFile::with_stem(image_data, "generated-image"),
+        };
+        self.bluebubbles
+            .send_attachment(&self.chat_guid, file)
+            .await?;
+
+        Ok(BlueBubblesAttachment {
+            guid: record.attachment_guid,
+            original_rowid: None,
+            mime_type: record.mime_type,
+            transfer_name: record.filename,
+            total_bytes: Some(record.size_bytes),
+        })
+    }
+
+    /// Downloads and prepares the images referenced in the conversation,
+    /// newest first. Only the triggering message's images are used unless
+    /// images from earlier in the context window are enabled.
+    async fn load_images(&self, messages: &[Message]) -> HashMap<String, VisionImage> {
+        let mut images = HashMap::new();
+        let message_count = if self.vision_context_images { messages.len() } else { 1 };
+
+        // Earlier turns (including the bot's generated images) only count when
+        // context images are enabled
+        for message in messages
+            .iter()
+            .rev()
+            .filter(|message| {
+                self.vision_context_images || matches!(message.role, MessageRole::User)
+            })
+            .take(message_count)
+        {
+            for attachment in &message.attachments {
+                if images.len() >= self.max_vision_images {
+                    debug!("Reached vision image limit of {}", self.max_vision_images);
+                    return images;
+                }
+
+                if !self.bluebubbles.is_image_attachment(attachment) {
+                    continue;
+                }
+
+                match self.load_image(attachment).await {
+                    Ok(image) => {
+                        images.insert(attachment.guid.clone(), image);
+                    }
+                    Err(e) => {
+                        error!("Failed to load image attachment {}: {}", attachment.guid, e);
                     }
-                    
-                    // We found the most recent user message but it has no images
-                    // Don't continue looking at older messages
-                    info!("Most recent user message has no images, not analyzing older messages");
-                    break;
                 }
             }
-            Err(e) => {
-                error!("Failed to fetch recent messages for image lookup: {}", e);
+        }
+
+        if !images.is_empty() {
+            info!("Attaching {} image(s) for vision in chat {}", images.len(), self.chat_guid);
+        }
+
+        images
+    }
+
+    /// Finds the most recent photo a user shared in the conversation.
+    async fn get_recent_user_image(&self) -> Option<VisionImage> {
+        let attachments = self
+            .context
+            .iter()
+            .rev()
+            .filter(|message| matches!(message.role, MessageRole::User))
+            .flat_map(|message| message.attachments.iter())
+            .filter(|attachment| self.bluebubbles.is_image_attachment(attachment));
+
+        for attachment in attachments {
+            match self.load_image(attachment).await {
+                Ok(image) => return Some(image),
+                Err(e) => error!("Failed to load image attachment {}: {}", attachment.guid, e),
             }
         }
 
         None
     }
+
+    async fn load_image(&self, attachment: &BlueBubblesAttachment) -> Result<VisionImage> {
+        let image_data = self.fetch_attachment(attachment).await?;
+        self.image_pipeline
+            .prepare(image_data, attachment.mime_type.as_deref())
+            .await
+    }
+
+    /// Transcribes voice memos and merges them with the typed text, so audio
+    /// reaches the model as the user's words.
+    async fn with_transcripts(&self, text: &str, attachments: &[BlueBubblesAttachment]) -> String {
+        let mut parts = Vec::new();
+        if !text.trim().is_empty() {
+            parts.push(text.to_string());
+        }
+
+        for attachment in attachments
+            .iter()
+            .filter(|attachment| self.bluebubbles.is_audio_attachment(attachment))
+        {
+            match self.transcribe_attachment(attachment).await {
+                Ok(transcript) if transcript.is_empty() => {
+                    debug!("Voice memo {} had no speech", attachment.guid);
+                }
+                Ok(transcript) if parts.is_empty() => parts.push(transcript),
+                Ok(transcript) => parts.push(format!("[Voice memo]: {}", transcript)),
+                Err(e) => {
+                    error!("Failed to transcribe attachment {}: {}", attachment.guid, e);
+                }
+            }
+        }
+
+        parts.join("\n")
+    }
+
+    async fn transcribe_attachment(&self, attachment: &BlueBubblesAttachment) -> Result<String> {
+        info!("Transcribing voice memo {} in chat {}", attachment.guid, self.chat_guid);
+
+        let audio_data = self.fetch_attachment(attachment).await?;
+        let audio = self
+            .audio_pipeline
+            .prepare(audio_data, attachment.transfer_name.as_deref())
+            .await?;
+        self.ai_clients.transcribe_audio(audio).await
+    }
+
+    /// Returns an attachment's bytes from the cache, downloading (and
+    /// caching) it if needed.
+    async fn fetch_attachment(&self, attachment: &BlueBubblesAttachment) -> Result<Vec<u8>> {
+        if let Some((_, data)) = self.attachment_cache.get(&attachment.guid).await? {
+            return Ok(data);
+        }
+
+        check_attachment_size(attachment, self.max_attachment_bytes)?;
+
+        let data = self.bluebubbles.download_attachment(attachment).await?;
+        if let Err(e) = self
+            .attachment_cache
+            .put(
+                &self.chat_guid,
+                attachment,
+                &data,
+                AttachmentSource::Downloaded,
+                None,
+            )
+            .await
+        {
+            error!("Failed to cache attachment {}: {}", attachment.guid, e);
+        }
+
+        Ok(data)
+    }
+}
+
+/// Parses the `[TOOL_CALL:<name>:<json args>]` marker produced by `AIClients`.
+fn parse_tool_call(response: &str) -> Option<(String, serde_json::Value)> {
+    let inner = response.strip_prefix("[TOOL_CALL:")?.strip_suffix(']')?;
+    let (name, args_json) = inner.split_once(':')?;
+    let args = serde_json::from_str(args_json).ok()?;
+    Some((name.to_string(), args))
+}
+
+fn string_arg<'a>(args: &'a serde_json::Value, name: &str) -> Result<&'a str> {
+    args.get(name)
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing '{}' argument", name))
+}
+
+fn tool_error_text(tool_name: &str) -> &'static str {
+    match tool_name {
+        "request_picture" => "❌ Failed to generate image. Please try again.",
+        "edit_image" => "❌ Couldn't edit that photo. Send a photo and try again.",
+        "resend_image" => "❌ Couldn't find that picture anymore.",
+        "send_file" => "❌ Couldn't send that file. Please try again.",
+        "set_reminder" => "❌ Couldn't set that reminder. Try a specific future time.",
+        "create_poll" => "❌ Couldn't start that poll. Polls need a question and 2-6 options.",
+        _ => "❌ Something went wrong. Please try again.",
+    }
 }
 
 pub struct ChatAgentHandle {
-    pub chat_guid: String,
     pub sender: mpsc::Sender<ChatAgentMessage>,
     pub task_handle: tokio::task::JoinHandle<Result<()>>,
 }
@@ -350,6 +944,22 @@ impl ChatAgentHandle {
         Ok(())
     }
 
+    pub async fn send_ambient(&self, messages: Vec<AmbientMessage>) -> Result<()> {
+        self.sender
+            .send(ChatAgentMessage::Ambient(messages))
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to send ambient messages to chat agent: {}", e))?;
+        Ok(())
+    }
+
+    pub async fn run_prompt(&self, job: RecurringJob) -> Result<()> {
+        self.sender
+            .send(ChatAgentMessage::RunPrompt(job))
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to send scheduled prompt to chat agent: {}", e))?;
+        Ok(())
+    }
+
     pub async fn shutdown(&self) -> Result<()> {
         self.sender
             .send(ChatAgentMessage::Shutdown)
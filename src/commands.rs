@@ -1,59 +1,538 @@
 use anyhow::Result;
-use regex::Regex;
+use regex::{Captures, Regex, RegexBuilder};
 use tracing::{debug, info};
-use crate::types::ChatConfig;
+use crate::types::{ChatConfig, ReplyMode};
 use crate::ai_clients::AIClients;
 use crate::database::Database;
+use crate::polls::{self, format_poll, parse_poll_spec, validate_poll};
+use crate::scheduling::{format_local, next_run, parse_recurrence, parse_timezone, resolve_timezone, start_of_day};
+use crate::triggers::{Trigger, MAX_CHAT_TRIGGERS};
+use crate::usage::{format_usage_report, UsageRecorder};
 use chrono::Utc;
 
 #[derive(Debug, Clone)]
 pub enum Command {
     Character { description: String },
+    RawCharacter { prompt: String },
+    PreviewCharacter { description: String },
+    UndoCharacter,
+    SavePersona { name: String },
+    UsePersona { name: String },
+    SharePersona { name: String },
+    ListPersonas,
+    ShowPersona { name: Option<String> },
+    CharacterUsage { usage: &'static str },
     Unhinge { enabled: bool },
     Name { trigger_name: String },
+    Voice { mode: ReplyMode, voice: Option<String> },
+    ShowConfig,
+    SetConfig { key: String, value: String },
+    Reset,
+    Forget { count: usize },
+    Help { command: Option<String> },
+    Bot { enabled: bool },
+    Proactive { enabled: bool },
+    AddTrigger { trigger: String },
+    RemoveTrigger { trigger: String },
+    ListTriggers,
+    AddOwner { handle: String },
+    RemoveOwner { handle: String },
+    ListOwners,
+    ClaimOwner,
+    ListReminders,
+    CancelReminder { id: i64 },
+    AddSchedule { when: String, prompt: String },
+    RemoveSchedule { id: i64 },
+    ListSchedules,
+    CreatePoll { question: String, options: Vec<String> },
+    ClosePoll { id: Option<i64> },
+    Usage { week: bool },
+}
+
+impl Command {
+    /// Registry name of the command this was parsed from.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Command::Character { .. }
+            | Command::RawCharacter { .. }
+            | Command::PreviewCharacter { .. }
+            | Command::UndoCharacter
+            | Command::SavePersona { .. }
+            | Command::UsePersona { .. }
+            | Command::SharePersona { .. }
+            | Command::ListPersonas
+            | Command::ShowPersona { .. }
+            | Command::CharacterUsage { .. } => "character",
+            Command::Unhinge { .. } => "unhinge",
+            Command::Name { .. } => "name",
+            Command::Voice { .. } => "voice",
+            Command::ShowConfig | Command::SetConfig { .. } => "config",
+            Command::Reset => "reset",
+            Command::Forget { .. } => "forget",
+            Command::Help { .. } => "help",
+            Command::Bot { .. } => "bot",
+            Command::Proactive { .. } => "proactive",
+            Command::AddTrigger { .. } | Command::RemoveTrigger { .. } | Command::ListTriggers => "trigger",
+            Command::AddOwner { .. }
+            | Command::RemoveOwner { .. }
+            | Command::ListOwners
+            | Command::ClaimOwner => "admin",
+            Command::ListReminders | Command::CancelReminder { .. } => "reminders",
+            Command::AddSchedule { .. } | Command::RemoveSchedule { .. } | Command::ListSchedules => "schedule",
+            Command::CreatePoll { .. } | Command::ClosePoll { .. } => "poll",
+            Command::Usage { .. } => "usage",
+        }
+    }
+
+    /// Permission needed to run this command. Read-only variants are open to
+    /// everyone; the rest use the level from the registry.
+    pub fn required_permission(&self) -> PermissionLevel {
+        match self {
+            Command::ShowConfig
+            | Command::ListPersonas
+            | Command::ShowPersona { .. }
+            | Command::CharacterUsage { .. }
+            | Command::ListOwners
+            | Command::ClaimOwner
+            | Command::ListTriggers
+            | Command::ListSchedules
+            | Command::Help { .. } => PermissionLevel::Everyone,
+            _ => find_command(self.name())
+                .map(|spec| spec.permission)
+                .unwrap_or(PermissionLevel::Admin),
+        }
+    }
+}
+
+/// Largest number of messages `@forget last N` will drop at once.
+const MAX_FORGET_COUNT: usize = 50;
+
+/// Largest context window `@config set context_size` accepts.
+const MAX_CONTEXT_SIZE: usize = 50;
+
+/// Longest follow-up window `@config set follow_up` accepts.
+const MAX_FOLLOW_UP_SECS: u64 = 600;
+
+/// Most recurring jobs a single chat can have.
+const MAX_CHAT_JOBS: usize = 10;
+
+/// How much of the character prompt `@config` shows.
+const PROMPT_PREVIEW_CHARS: usize = 120;
+
+/// What a command did to the conversation history, so the chat agent can
+/// bring its in-memory context in line with the database.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContextChange {
+    Keep,
+    Clear,
+    ForgetLast(usize),
+}
+
+/// What a character change does to the undo history once the new prompt is
+/// saved.
+#[derive(Debug, Clone, Copy)]
+enum HistoryUpdate {
+    /// Remember the old prompt for `@character undo`.
+    Remember,
+    /// The new prompt came from this history entry, so drop it.
+    Restored(i64),
+}
+
+#[derive(Debug, Clone)]
+pub struct CommandResponse {
+    pub reply: String,
+    pub context: ContextChange,
+}
+
+impl CommandResponse {
+    pub fn text(reply: impl Into<String>) -> Self {
+        Self::with_context(reply, ContextChange::Keep)
+    }
+
+    pub fn with_context(reply: impl Into<String>, context: ContextChange) -> Self {
+        Self {
+            reply: reply.into(),
+            context,
+        }
+    }
+}
+
+/// Who is allowed to run a command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PermissionLevel {
+    Everyone,
+    Owner,
+    Admin,
+}
+
+impl PermissionLevel {
+    pub fn describe(&self) -> &'static str {
+        match self {
+            PermissionLevel::Everyone => "everyone",
+            PermissionLevel::Owner => "chat owners and bot admins",
+            PermissionLevel::Admin => "bot admins",
+        }
+    }
+}
+
+/// A chat command: how it's written, what it does, who may run it and how
+/// it's parsed. `COMMANDS` drives both parsing and `@help`. Patterns are
+/// matched case-insensitively against the start of the message, so a command
+/// mentioned mid-sentence is just chat.
+pub struct CommandSpec {
+    pub name: &'static str,
+    pub syntax: &'static str,
+    pub description: &'static str,
+    pub permission: PermissionLevel,
+    pattern: &'static str,
+    parse: fn(&Captures) -> Option<Command>,
+}
+
+pub const COMMANDS: &[CommandSpec] = &[
+    CommandSpec {
+        name: "help",
+        syntax: "@help [command]",
+        description: "List commands, or show details for one",
+        permission: PermissionLevel::Everyone,
+        pattern: r"@help(?:\s+@?(\w+))?\s*$",
+        parse: parse_help,
+    },
+    CommandSpec {
+        name: "character",
+        syntax: "@character <description> | raw <prompt> | preview <description> | undo | save <name> | use <name> | share <name> | list | show [name]",
+        description: "Give the bot a new AI-generated personality, edit or undo the prompt, or manage saved personas",
+        permission: PermissionLevel::Owner,
+        pattern: r"(?s)@character\s+(.+)",
+        parse: parse_character,
+    },
+    CommandSpec {
+        name: "unhinge",
+        syntax: "@unhinge <on|off>",
+        description: "Switch between OpenAI and the local Ollama model",
+        permission: PermissionLevel::Owner,
+        pattern: r"@unhinge\s+(.+)",
+        parse: parse_unhinge,
+    },
+    CommandSpec {
+        name: "name",
+        syntax: "@name <name>",
+        description: "Change the name the bot answers to",
+        permission: PermissionLevel::Owner,
+        pattern: r"@name\s+(\S+)\s*$",
+        parse: parse_name,
+    },
+    CommandSpec {
+        name: "trigger",
+        syntax: "@trigger add <word|emoji|/regex/> | remove <trigger> | list",
+        description: "Add extra words, emoji or regexes that wake the bot up",
+        permission: PermissionLevel::Owner,
+        pattern: r"@trigger\s+(add|remove|list)(?:\s+(.+?))?\s*$",
+        parse: parse_trigger,
+    },
+    CommandSpec {
+        name: "voice",
+        syntax: "@voice <on|off|both> [voice]",
+        description: "Reply with voice memos, optionally also as text",
+        permission: PermissionLevel::Owner,
+        pattern: r"@voice\s+(\S+)(?:\s+(\S+))?\s*$",
+        parse: parse_voice,
+    },
+    CommandSpec {
+        name: "config",
+        syntax: "@config [set <key> <value>]",
+        description: "Show this chat's settings, or change model, temperature, reply_mode, context_size, follow_up, intent_threshold or timezone",
+        permission: PermissionLevel::Owner,
+        pattern: r"@config(?:\s+show|\s+set\s+(\S+)\s+(.+))?\s*$",
+        parse: parse_config,
+    },
+    CommandSpec {
+        name: "reset",
+        syntax: "@reset",
+        description: "Clear the conversation history for this chat",
+        permission: PermissionLevel::Owner,
+        pattern: r"@reset\s*$",
+        parse: parse_reset,
+    },
+    CommandSpec {
+        name: "forget",
+        syntax: "@forget last <n>",
+        description: "Forget the last n messages of the conversation",
+        permission: PermissionLevel::Owner,
+        pattern: r"@forget\s+last(?:\s+(\d+))?\s*$",
+        parse: parse_forget,
+    },
+    CommandSpec {
+        name: "admin",
+        syntax: "@admin claim | add <handle> | remove <handle> | list",
+        description: "Become the first owner of this chat's bot settings, or manage who owns them",
+        permission: PermissionLevel::Owner,
+        pattern: r"@admin\s+(add|remove|list|claim)(?:\s+(.+?))?\s*$",
+        parse: parse_admin,
+    },
+    CommandSpec {
+        name: "bot",
+        syntax: "@bot <on|off>",
+        description: "Pause or resume the bot in this chat",
+        permission: PermissionLevel::Owner,
+        pattern: r"@bot\s+(on|off)\s*$",
+        parse: parse_bot,
+    },
+    CommandSpec {
+        name: "proactive",
+        syntax: "@proactive <on|off>",
+        description: "Let the bot chime in now and then without being asked",
+        permission: PermissionLevel::Owner,
+        pattern: r"@proactive\s+(on|off)\s*$",
+        parse: parse_proactive,
+    },
+    CommandSpec {
+        name: "reminders",
+        syntax: "@reminders [cancel <id>]",
+        description: "List upcoming reminders in this chat, or cancel one",
+        permission: PermissionLevel::Everyone,
+        pattern: r"@reminders(?:\s+cancel\s+#?(\d+))?\s*$",
+        parse: parse_reminders,
+    },
+    CommandSpec {
+        name: "schedule",
+        syntax: "@schedule add <when>: <prompt> | remove <id> | list",
+        description: "Run a prompt on a schedule and post the answer, e.g. @schedule add every weekday 8am: a motivational quote",
+        permission: PermissionLevel::Owner,
+        pattern: r"(?s)@schedule\s+(add|remove|list)(?:\s+(.+?))?\s*$",
+        parse: parse_schedule,
+    },
+    CommandSpec {
+        name: "poll",
+        syntax: "@poll \"question\" option | option | ... | close [id]",
+        description: "Ask the chat to vote by number or tapback, or close a poll and show the results",
+        permission: PermissionLevel::Everyone,
+        pattern: r"(?s)@poll\s+(.+?)\s*$",
+        parse: parse_poll,
+    },
+    CommandSpec {
+        name: "usage",
+        syntax: "@usage [today|week]",
+        description: "Show AI requests, tokens and estimated cost for this chat",
+        permission: PermissionLevel::Everyone,
+        pattern: r"@usage(?:\s+(today|week))?\s*$",
+        parse: parse_usage,
+    },
+];
+
+pub fn find_command(name: &str) -> Option<&'static CommandSpec> {
+    let name = name.trim_start_matches('@').to_lowercase();
+    COMMANDS.iter().find(|spec| spec.name == name)
+}
+
+fn parse_help(captures: &Captures) -> Option<Command> {
+    let command = captures.get(1).map(|m| m.as_str().to_lowercase());
+    Some(Command::Help { command })
+}
+
+fn parse_character(captures: &Captures) -> Option<Command> {
+    let description = captures.get(1)?.as_str().trim().to_string();
+    if description.is_empty() {
+        return None;
+    }
+
+    // Prompt subcommands keep the rest of the text as-is
+    if let Some((subcommand, rest)) = description.split_once(char::is_whitespace) {
+        let rest = rest.trim().to_string();
+        match subcommand.to_lowercase().as_str() {
+            "raw" => return Some(Command::RawCharacter { prompt: rest }),
+            "preview" => return Some(Command::PreviewCharacter { description: rest }),
+            _ => {}
+        }
+    } else if description.eq_ignore_ascii_case("undo") {
+        return Some(Command::UndoCharacter);
+    } else if let Some(usage) = character_usage(&description) {
+        // A subcommand with nothing after it is a mistake, not a personality
+        return Some(Command::CharacterUsage { usage });
+    }
+
+    // Persona subcommands take a single name; anything else is a description
+    let words: Vec<&str> = description.split_whitespace().collect();
+    let name = words.get(1).map(|name| name.to_lowercase());
+    let persona_command = match (words[0].to_lowercase().as_str(), words.len()) {
+        ("list", 1) => Some(Command::ListPersonas),
+        ("show", 1) => Some(Command::ShowPersona { name: None }),
+        ("show", 2) => Some(Command::ShowPersona { name }),
+        ("save", 2) => name.map(|name| Command::SavePersona { name }),
+        ("use", 2) => name.map(|name| Command::UsePersona { name }),
+        ("share", 2) => name.map(|name| Command::SharePersona { name }),
+        _ => None,
+    };
+
+    Some(persona_command.unwrap_or(Command::Character { description }))
+}
+
+fn character_usage(subcommand: &str) -> Option<&'static str> {
+    match subcommand.to_lowercase().as_str() {
+        "raw" => Some("@character raw <prompt>"),
+        "preview" => Some("@character preview <description>"),
+        "save" => Some("@character save <name>"),
+        "use" => Some("@character use <name>"),
+        "share" => Some("@character share <name>"),
+        _ => None,
+    }
+}
+
+fn parse_unhinge(captures: &Captures) -> Option<Command> {
+    let value = captures.get(1)?.as_str().trim().to_lowercase();
+    let enabled = value == "true" || value == "1" || value == "on" || value == "yes";
+    Some(Command::Unhinge { enabled })
+}
+
+fn parse_name(captures: &Captures) -> Option<Command> {
+    let trigger_name = captures.get(1)?.as_str().trim().to_lowercase();
+    if trigger_name.is_empty() || !trigger_name.chars().all(|c| c.is_alphanumeric()) {
+        return None;
+    }
+    Some(Command::Name { trigger_name })
+}
+
+fn parse_voice(captures: &Captures) -> Option<Command> {
+    let mode = ReplyMode::parse(captures.get(1)?.as_str())?;
+    let voice = captures.get(2).map(|m| m.as_str().to_lowercase());
+    Some(Command::Voice { mode, voice })
+}
+
+fn parse_config(captures: &Captures) -> Option<Command> {
+    match (captures.get(1), captures.get(2)) {
+        (Some(key), Some(value)) => Some(Command::SetConfig {
+            key: key.as_str().to_lowercase().replace('-', "_"),
+            value: value.as_str().trim().to_string(),
+        }),
+        _ => Some(Command::ShowConfig),
+    }
+}
+
+fn parse_admin(captures: &Captures) -> Option<Command> {
+    let action = captures.get(1)?.as_str().to_lowercase();
+    let handle = captures.get(2).map(|m| m.as_str().to_string());
+    match (action.as_str(), handle) {
+        ("add", Some(handle)) => Some(Command::AddOwner { handle }),
+        ("remove", Some(handle)) => Some(Command::RemoveOwner { handle }),
+        ("list", None) => Some(Command::ListOwners),
+        ("claim", None) => Some(Command::ClaimOwner),
+        _ => None,
+    }
+}
+
+fn parse_trigger(captures: &Captures) -> Option<Command> {
+    let action = captures.get(1)?.as_str().to_lowercase();
+    let trigger = captures.get(2).map(|m| m.as_str().to_string());
+    match (action.as_str(), trigger) {
+        ("add", Some(trigger)) => Some(Command::AddTrigger { trigger }),
+        ("remove", Some(trigger)) => Some(Command::RemoveTrigger { trigger }),
+        ("list", None) => Some(Command::ListTriggers),
+        _ => None,
+    }
+}
+
+fn parse_bot(captures: &Captures) -> Option<Command> {
+    let enabled = captures.get(1)?.as_str().eq_ignore_ascii_case("on");
+    Some(Command::Bot { enabled })
+}
+
+fn parse_proactive(captures: &Captures) -> Option<Command> {
+    let enabled = captures.get(1)?.as_str().eq_ignore_ascii_case("on");
+    Some(Command::Proactive { enabled })
+}
+
+fn parse_reminders(captures: &Captures) -> Option<Command> {
+    match captures.get(1) {
+        Some(id) => Some(Command::CancelReminder { id: id.as_str().parse().ok()? }),
+        None => Some(Command::ListReminders),
+    }
+}
+
+fn parse_schedule(captures: &Captures) -> Option<Command> {
+    let action = captures.get(1)?.as_str().to_lowercase();
+    let rest = captures.get(2).map(|m| m.as_str().trim());
+    match (action.as_str(), rest) {
+        ("add", Some(rest)) => {
+            // The time can contain a colon ("8:30am"), so split on ": "
+            let (when, prompt) = rest.split_once(": ")?;
+            let prompt = prompt.trim();
+            if prompt.is_empty() {
+                return None;
+            }
+            Some(Command::AddSchedule {
+                when: when.trim().to_string(),
+                prompt: prompt.to_string(),
+            })
+        }
+        ("remove", Some(id)) => Some(Command::RemoveSchedule {
+            id: id.trim_start_matches('#').parse().ok()?,
+        }),
+        ("list", None) => Some(Command::ListSchedules),
+        _ => None,
+    }
+}
+
+fn parse_poll(captures: &Captures) -> Option<Command> {
+    let spec = captures.get(1)?.as_str();
+    let words: Vec<&str> = spec.split_whitespace().collect();
+    if words[0].eq_ignore_ascii_case("close") {
+        return match words.get(1..) {
+            Some([]) => Some(Command::ClosePoll { id: None }),
+            Some([id]) => Some(Command::ClosePoll { id: Some(id.trim_start_matches('#').parse().ok()?) }),
+            _ => None,
+        };
+    }
+
+    let (question, options) = parse_poll_spec(spec)?;
+    Some(Command::CreatePoll { question, options })
+}
+
+fn parse_usage(captures: &Captures) -> Option<Command> {
+    let week = captures
+        .get(1)
+        .is_some_and(|period| period.as_str().eq_ignore_ascii_case("week"));
+    Some(Command::Usage { week })
+}
+
+fn parse_reset(_: &Captures) -> Option<Command> {
+    Some(Command::Reset)
+}
+
+fn parse_forget(captures: &Captures) -> Option<Command> {
+    let count = match captures.get(1) {
+        Some(m) => m.as_str().parse().ok()?,
+        None => 1,
+    };
+    Some(Command::Forget { count })
 }
 
 pub struct CommandParser {
-    character_regex: Regex,
-    unhinge_regex: Regex,
-    name_regex: Regex,
+    commands: Vec<(&'static CommandSpec, Regex)>,
 }
 
 impl CommandParser {
     pub fn new() -> Result<Self> {
-        Ok(Self {
-            character_regex: Regex::new(r"@character\s+(.+)")?,
-            unhinge_regex: Regex::new(r"@unhinge\s+(.+)")?,
-            name_regex: Regex::new(r"@name\s+(\w+)")?,
-        })
+        let commands = COMMANDS
+            .iter()
+            .map(|spec| {
+                let regex = RegexBuilder::new(&format!("^(?:{})", spec.pattern))
+                    .case_insensitive(true)
+                    .build()?;
+                Ok((spec, regex))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self { commands })
     }
 
     pub fn parse_command(&self, text: &str) -> Option<Command> {
         let text = text.trim();
 
-        // Check for character command
-        if let Some(captures) = self.character_regex.captures(text) {
-            let description = captures.get(1)?.as_str().trim().to_string();
-            if !description.is_empty() {
-                debug!("Parsed character command: {}", description);
-                return Some(Command::Character { description });
-            }
-        }
-
-        // Check for unhinge command
-        if let Some(captures) = self.unhinge_regex.captures(text) {
-            let value = captures.get(1)?.as_str().trim().to_lowercase();
-            let enabled = value == "true" || value == "1" || value == "on" || value == "yes";
-            debug!("Parsed unhinge command: {}", enabled);
-            return Some(Command::Unhinge { enabled });
-        }
-
-        // Check for name command
-        if let Some(captures) = self.name_regex.captures(text) {
-            let trigger_name = captures.get(1)?.as_str().trim().to_lowercase();
-            if !trigger_name.is_empty() && trigger_name.chars().all(|c| c.is_alphanumeric()) {
-                debug!("Parsed name command: {}", trigger_name);
-                return Some(Command::Name { trigger_name });
+        for (spec, regex) in &self.commands {
+            if let Some(captures) = regex.captures(text) {
+                if let Some(command) = (spec.parse)(&captures) {
+                    debug!("Parsed {} command: {:?}", spec.name, command);
+                    return Some(command);
+                }
             }
         }
 
@@ -65,27 +544,80 @@ pub struct CommandHandler {
     parser: CommandParser,
     ai_clients: AIClients,
     database: Database,
+    admin_handles: Vec<String>,
+    default_timezone: String,
+    poll_timeout_hours: i64,
+    usage: UsageRecorder,
 }
 
 impl CommandHandler {
-    pub fn new(ai_clients: AIClients, database: Database) -> Result<Self> {
+    pub fn new(
+        ai_clients: AIClients,
+        database: Database,
+        admin_handles: Vec<String>,
+        default_timezone: String,
+        poll_timeout_hours: i64,
+        usage: UsageRecorder,
+    ) -> Result<Self> {
         Ok(Self {
             parser: CommandParser::new()?,
             ai_clients,
             database,
+            admin_handles,
+            default_timezone,
+            poll_timeout_hours,
+            usage,
         })
     }
 
     pub async fn handle_command(
         &self,
         chat_guid: &str,
+        sender: Option<&str>,
         text: &str,
         config: &mut ChatConfig,
-    ) -> Result<Option<String>> {
+    ) -> Result<Option<CommandResponse>> {
         if let Some(command) = self.parser.parse_command(text) {
-            match command {
+            let required = command.required_permission();
+            if required > PermissionLevel::Everyone {
+                let level = self.permission_level(chat_guid, sender).await?;
+                if level < required {
+                    info!(
+                        "Denied @{} in chat {} for {:?}",
+                        command.name(),
+                        chat_guid,
+                        sender
+                    );
+                    return Ok(Some(CommandResponse::text(format!(
+                        "❌ Only {} can use @{}",
+                        required.describe(),
+                        command.name()
+                    ))));
+                }
+            }
+
+            let response = match command {
                 Command::Character { description } => {
-                    self.handle_character_command(chat_guid, &description, config).await
+                    self.handle_character_command(chat_guid, sender, &description, config).await
+                }
+                Command::RawCharacter { prompt } => {
+                    let reply = "✅ Character prompt set".to_string();
+                    self.change_character(chat_guid, Some(prompt), HistoryUpdate::Remember, config, reply)
+                        .await
+                }
+                Command::PreviewCharacter { description } => {
+                    self.handle_preview_character(chat_guid, sender, &description).await
+                }
+                Command::UndoCharacter => self.handle_undo_character(chat_guid, config).await,
+                Command::CharacterUsage { usage } => {
+                    Ok(CommandResponse::text(format!("❌ Usage: {}", usage)))
+                }
+                Command::SavePersona { name } => self.handle_save_persona(chat_guid, &name, config).await,
+                Command::UsePersona { name } => self.handle_use_persona(chat_guid, &name, config).await,
+                Command::SharePersona { name } => self.handle_share_persona(chat_guid, &name).await,
+                Command::ListPersonas => self.handle_list_personas(chat_guid).await,
+                Command::ShowPersona { name } => {
+                    self.handle_show_persona(chat_guid, name.as_deref(), config).await
                 }
                 Command::Unhinge { enabled } => {
                     self.handle_unhinge_command(chat_guid, enabled, config).await
@@ -93,60 +625,663 @@ impl CommandHandler {
                 Command::Name { trigger_name } => {
                     self.handle_name_command(chat_guid, &trigger_name, config).await
                 }
-            }
+                Command::Voice { mode, voice } => {
+                    self.handle_voice_command(chat_guid, mode, voice, config).await
+                }
+                Command::ShowConfig => Ok(CommandResponse::text(self.describe_config(config))),
+                Command::SetConfig { key, value } => {
+                    self.handle_set_config_command(chat_guid, &key, &value, config).await
+                }
+                Command::Reset => self.handle_reset_command(chat_guid).await,
+                Command::Forget { count } => self.handle_forget_command(chat_guid, count).await,
+                Command::Help { command } => Ok(CommandResponse::text(help_text(command.as_deref()))),
+                Command::Bot { enabled } => self.handle_bot_command(chat_guid, enabled, config).await,
+                Command::Proactive { enabled } => {
+                    self.handle_proactive_command(chat_guid, enabled, config).await
+                }
+                Command::AddTrigger { trigger } => self.handle_add_trigger(chat_guid, &trigger, config).await,
+                Command::RemoveTrigger { trigger } => {
+                    self.handle_remove_trigger(chat_guid, &trigger, config).await
+                }
+                Command::ListTriggers => Ok(CommandResponse::text(describe_triggers(config))),
+                Command::AddOwner { handle } => self.handle_add_owner(chat_guid, sender, &handle).await,
+                Command::RemoveOwner { handle } => {
+                    self.handle_remove_owner(chat_guid, sender, &handle).await
+                }
+                Command::ClaimOwner => self.handle_claim_owner(chat_guid, sender).await,
+                Command::ListOwners => self.handle_list_owners(chat_guid).await,
+                Command::ListReminders => self.handle_list_reminders(chat_guid, config).await,
+                Command::CancelReminder { id } => self.handle_cancel_reminder(chat_guid, id).await,
+                Command::AddSchedule { when, prompt } => {
+                    self.handle_add_schedule(chat_guid, &when, &prompt, config).await
+                }
+                Command::RemoveSchedule { id } => self.handle_remove_schedule(chat_guid, id).await,
+                Command::ListSchedules => self.handle_list_schedules(chat_guid, config).await,
+                Command::CreatePoll { question, options } => {
+                    self.handle_create_poll(chat_guid, &question, &options).await
+                }
+                Command::ClosePoll { id } => self.handle_close_poll(chat_guid, id).await,
+                Command::Usage { week } => self.handle_usage(chat_guid, week, config).await,
+            };
+            response.map(Some)
         } else {
             Ok(None)
         }
     }
 
+    /// Works out what a sender may do in a chat. Until a chat has owners and
+    /// there are no global admins, everyone is treated as an owner so the
+    /// bot keeps working out of the box.
+    async fn permission_level(&self, chat_guid: &str, sender: Option<&str>) -> Result<PermissionLevel> {
+        let sender = sender.map(normalize_handle);
+
+        if let Some(sender) = &sender {
+            if self.admin_handles.contains(sender) {
+                return Ok(PermissionLevel::Admin);
+            }
+        }
+
+        let owners = self.database.get_chat_owners(chat_guid).await?;
+        if owners.is_empty() && self.admin_handles.is_empty() {
+            return Ok(PermissionLevel::Owner);
+        }
+
+        match sender {
+            Some(sender) if owners.contains(&sender) => Ok(PermissionLevel::Owner),
+            _ => Ok(PermissionLevel::Everyone),
+        }
+    }
+
+    async fn handle_proactive_command(
+        &self,
+        chat_guid: &str,
+        enabled: bool,
+        config: &mut ChatConfig,
+    ) -> Result<CommandResponse> {
+        info!("Handling proactive command for chat {}: {}", chat_guid, enabled);
+
+        config.proactive = enabled;
+        config.updated_at = Utc::now();
+
+        // Save to database
+        if let Err(e) = self.database.save_chat_config(config).await {
+            return Ok(CommandResponse::text(format!(
+                "❌ Failed to save proactive mode: {}",
+                e
+            )));
+        }
+
+        Ok(CommandResponse::text(if enabled {
+            "✅ Proactive mode on. I'll occasionally join the conversation on my own"
+        } else {
+            "✅ Proactive mode off. I'll only speak when spoken to"
+        }))
+    }
+
+    async fn handle_add_trigger(
+        &self,
+        chat_guid: &str,
+        trigger: &str,
+        config: &mut ChatConfig,
+    ) -> Result<CommandResponse> {
+        info!("Adding trigger for chat {}: {}", chat_guid, trigger);
+
+        if let Err(message) = Trigger::parse(trigger) {
+            return Ok(CommandResponse::text(format!("❌ {}", message)));
+        }
+
+        let trigger = trigger.trim().to_string();
+        if config.triggers.contains(&trigger) {
+            return Ok(CommandResponse::text(format!("'{}' is already a trigger", trigger)));
+        }
+        if config.triggers.len() >= MAX_CHAT_TRIGGERS {
+            return Ok(CommandResponse::text(format!(
+                "❌ A chat can have at most {} custom triggers",
+                MAX_CHAT_TRIGGERS
+            )));
+        }
+
+        config.triggers.push(trigger.clone());
+        config.updated_at = Utc::now();
+
+        // Save to database
+        if let Err(e) = self.database.save_chat_config(config).await {
+            return Ok(CommandResponse::text(format!(
+                "❌ Failed to save trigger: {}",
+                e
+            )));
+        }
+
+        Ok(CommandResponse::text(format!("✅ Added trigger '{}'", trigger)))
+    }
+
+    async fn handle_remove_trigger(
+        &self,
+        chat_guid: &str,
+        trigger: &str,
+        config: &mut ChatConfig,
+    ) -> Result<CommandResponse> {
+        info!("Removing trigger for chat {}: {}", chat_guid, trigger);
+
+        let trigger = trigger.trim();
+        let before = config.triggers.len();
+        config.triggers.retain(|t| t != trigger);
+        if config.triggers.len() == before {
+            return Ok(CommandResponse::text(format!("❌ '{}' isn't one of this chat's triggers", trigger)));
+        }
+        config.updated_at = Utc::now();
+
+        // Save to database
+        if let Err(e) = self.database.save_chat_config(config).await {
+            return Ok(CommandResponse::text(format!(
+                "❌ Failed to save triggers: {}",
+                e
+            )));
+        }
+
+        Ok(CommandResponse::text(format!("✅ Removed trigger '{}'", trigger)))
+    }
+
+    async fn handle_bot_command(
+        &self,
+        chat_guid: &str,
+        enabled: bool,
+        config: &mut ChatConfig,
+    ) -> Result<CommandResponse> {
+        info!("Handling bot command for chat {}: {}", chat_guid, enabled);
+
+        config.paused = !enabled;
+        config.updated_at = Utc::now();
+
+        // Save to database
+        if let Err(e) = self.database.save_chat_config(config).await {
+            return Ok(CommandResponse::text(format!(
+                "❌ Failed to save bot state: {}",
+                e
+            )));
+        }
+
+        Ok(CommandResponse::text(if enabled {
+            "✅ I'm back! Say @bot off to pause me again"
+        } else {
+            "✅ Paused. I'll stay quiet here until someone says @bot on"
+        }))
+    }
+
+    /// Makes the sender the first owner of a chat nobody owns yet. Only
+    /// works in the default setup without `ADMIN_HANDLES`; with admins, they
+    /// assign owners.
+    async fn handle_claim_owner(&self, chat_guid: &str, sender: Option<&str>) -> Result<CommandResponse> {
+        let Some(sender) = sender.map(normalize_handle).filter(|sender| !sender.is_empty()) else {
+            return Ok(CommandResponse::text("❌ I can't tell who you are, so I can't make you the owner"));
+        };
+
+        if !self.database.get_chat_owners(chat_guid).await?.is_empty() {
+            return Ok(CommandResponse::text(
+                "❌ This chat already has an owner. Ask them to @admin add you",
+            ));
+        }
+        if !self.admin_handles.is_empty() && !self.admin_handles.contains(&sender) {
+            return Ok(CommandResponse::text("❌ Ask a bot admin to add an owner for this chat"));
+        }
+
+        self.database.add_chat_owner(chat_guid, &sender).await?;
+        info!("{} claimed ownership of chat {}", sender, chat_guid);
+        Ok(CommandResponse::text(format!(
+            "✅ {} now owns this chat. Only owners can change the bot here",
+            sender
+        )))
+    }
+
+    /// Adding or removing owners needs a real owner or admin. In an unowned
+    /// chat everyone counts as an owner, so that alone isn't enough; someone
+    /// has to claim it first.
+    async fn check_owner_change(&self, chat_guid: &str, sender: Option<&str>) -> Result<Option<CommandResponse>> {
+        let is_admin = sender.is_some_and(|sender| self.admin_handles.contains(&normalize_handle(sender)));
+        if !is_admin && self.database.get_chat_owners(chat_guid).await?.is_empty() {
+            return Ok(Some(CommandResponse::text(
+                "❌ Nobody owns this chat yet. Say @admin claim to become its owner first",
+            )));
+        }
+        Ok(None)
+    }
+
+    async fn handle_add_owner(&self, chat_guid: &str, sender: Option<&str>, handle: &str) -> Result<CommandResponse> {
+        info!("Adding owner for chat {}: {}", chat_guid, handle);
+
+        if let Some(refusal) = self.check_owner_change(chat_guid, sender).await? {
+            return Ok(refusal);
+        }
+
+        let handle = normalize_handle(handle);
+        if handle.is_empty() {
+            return Ok(CommandResponse::text("❌ Give a phone number or email address"));
+        }
+
+        if !self.database.add_chat_owner(chat_guid, &handle).await? {
+            return Ok(CommandResponse::text(format!("{} is already an owner of this chat", handle)));
+        }
+
+        Ok(CommandResponse::text(format!("✅ {} is now an owner of this chat", handle)))
+    }
+
+    async fn handle_remove_owner(
+        &self,
+        chat_guid: &str,
+        sender: Option<&str>,
+        handle: &str,
+    ) -> Result<CommandResponse> {
+        info!("Removing owner for chat {}: {}", chat_guid, handle);
+
+        if let Some(refusal) = self.check_owner_change(chat_guid, sender).await? {
+            return Ok(refusal);
+        }
+
+        let handle = normalize_handle(handle);
+        if !self.database.remove_chat_owner(chat_guid, &handle).await? {
+            return Ok(CommandResponse::text(format!("❌ {} isn't an owner of this chat", handle)));
+        }
+
+        Ok(CommandResponse::text(format!("✅ {} is no longer an owner of this chat", handle)))
+    }
+
+    async fn handle_list_owners(&self, chat_guid: &str) -> Result<CommandResponse> {
+        let owners = self.database.get_chat_owners(chat_guid).await?;
+        if owners.is_empty() {
+            let reply = if self.admin_handles.is_empty() {
+                "This chat has no owners yet, so everyone can change the bot. Say @admin claim to become its owner"
+            } else {
+                "This chat has no owners yet; only bot admins can change the bot"
+            };
+            return Ok(CommandResponse::text(reply));
+        }
+
+        let mut lines = vec!["Chat owners:".to_string()];
+        lines.extend(owners.iter().map(|owner| format!("• {}", owner)));
+        Ok(CommandResponse::text(lines.join("\n")))
+    }
+
+    async fn handle_list_reminders(&self, chat_guid: &str, config: &ChatConfig) -> Result<CommandResponse> {
+        let reminders = self.database.get_pending_scheduled_messages(chat_guid).await?;
+        if reminders.is_empty() {
+            return Ok(CommandResponse::text(
+                "No reminders set. Ask me to remind you about something!",
+            ));
+        }
+
+        let tz = resolve_timezone(config.timezone.as_deref(), &self.default_timezone);
+        let mut lines = vec![format!("Upcoming reminders ({}):", tz.name())];
+        lines.extend(reminders.iter().map(|reminder| {
+            format!(
+                "• #{} {} — {}",
+                reminder.id,
+                format_local(reminder.send_at, tz),
+                reminder.message
+            )
+        }));
+        lines.push("Say @reminders cancel <id> to cancel one.".to_string());
+        Ok(CommandResponse::text(lines.join("\n")))
+    }
+
+    async fn handle_cancel_reminder(&self, chat_guid: &str, id: i64) -> Result<CommandResponse> {
+        if self.database.cancel_scheduled_message(chat_guid, id).await? {
+            info!("Cancelled reminder #{} in chat {}", id, chat_guid);
+            Ok(CommandResponse::text(format!("✅ Cancelled reminder #{}", id)))
+        } else {
+            Ok(CommandResponse::text(format!("❌ No upcoming reminder #{} in this chat", id)))
+        }
+    }
+
+    async fn handle_usage(&self, chat_guid: &str, week: bool, config: &ChatConfig) -> Result<CommandResponse> {
+        // Days start at midnight in the chat's own time zone
+        let tz = resolve_timezone(config.timezone.as_deref(), &self.default_timezone);
+        let (period, days_back) = if week { ("this week", 6) } else { ("today", 0) };
+        let since = start_of_day(tz, Utc::now(), days_back);
+
+        let models = self.database.get_usage_summary(chat_guid, since).await?;
+        let senders = self.database.get_usage_by_sender(chat_guid, since, 5).await?;
+        Ok(CommandResponse::text(format_usage_report(period, &models, &senders)))
+    }
+
+    async fn handle_add_schedule(
+        &self,
+        chat_guid: &str,
+        when: &str,
+        prompt: &str,
+        config: &ChatConfig,
+    ) -> Result<CommandResponse> {
+        let schedule = match parse_recurrence(when) {
+            Ok(schedule) => schedule,
+            Err(message) => return Ok(CommandResponse::text(format!("❌ {}", message))),
+        };
+        let tz = resolve_timezone(config.timezone.as_deref(), &self.default_timezone);
+        let Some(first_run) = next_run(&schedule, tz, Utc::now()) else {
+            return Ok(CommandResponse::text("❌ That schedule never comes around"));
+        };
+
+        if self.database.get_recurring_jobs(chat_guid).await?.len() >= MAX_CHAT_JOBS {
+            return Ok(CommandResponse::text(format!(
+                "❌ This chat already has {} scheduled prompts. Remove one first",
+                MAX_CHAT_JOBS
+            )));
+        }
+
+        let id = self
+            .database
+            .add_recurring_job(chat_guid, &schedule, when, prompt, first_run)
+            .await?;
+        info!("Added recurring job #{} ({}) for chat {}", id, schedule, chat_guid);
+        Ok(CommandResponse::text(format!(
+            "✅ Scheduled #{} ({}). First run: {}",
+            id,
+            when,
+            format_local(first_run, tz)
+        )))
+    }
+
+    async fn handle_remove_schedule(&self, chat_guid: &str, id: i64) -> Result<CommandResponse> {
+        if self.database.delete_recurring_job(chat_guid, id).await? {
+            info!("Removed recurring job #{} from chat {}", id, chat_guid);
+            Ok(CommandResponse::text(format!("✅ Removed scheduled prompt #{}", id)))
+        } else {
+            Ok(CommandResponse::text(format!("❌ No scheduled prompt #{} in this chat", id)))
+        }
+    }
+
+    async fn handle_list_schedules(&self, chat_guid: &str, config: &ChatConfig) -> Result<CommandResponse> {
+        let jobs = self.database.get_recurring_jobs(chat_guid).await?;
+        if jobs.is_empty() {
+            return Ok(CommandResponse::text(
+                "No scheduled prompts. Add one with @schedule add <when>: <prompt>",
+            ));
+        }
+
+        let tz = resolve_timezone(config.timezone.as_deref(), &self.default_timezone);
+        let mut lines = vec!["Scheduled prompts:".to_string()];
+        lines.extend(jobs.iter().map(|job| {
+            format!(
+                "• #{} {}: {} (next: {})",
+                job.id,
+                job.description,
+                job.prompt,
+                format_local(job.next_run_at, tz)
+            )
+        }));
+        Ok(CommandResponse::text(lines.join("\n")))
+    }
+
+    async fn handle_create_poll(
+        &self,
+        chat_guid: &str,
+        question: &str,
+        options: &[String],
+    ) -> Result<CommandResponse> {
+        if let Err(message) = validate_poll(question, options) {
+            return Ok(CommandResponse::text(format!("❌ {}", message)));
+        }
+
+        let closes_at = Utc::now() + chrono::Duration::hours(self.poll_timeout_hours);
+        let poll = self
+            .database
+            .create_poll(chat_guid, question, options, closes_at)
+            .await?;
+        info!("Created poll #{} in chat {}", poll.id, chat_guid);
+        Ok(CommandResponse::text(format_poll(&poll)))
+    }
+
+    async fn handle_close_poll(&self, chat_guid: &str, id: Option<i64>) -> Result<CommandResponse> {
+        let poll = match id {
+            Some(id) => self.database.get_open_poll_by_id(chat_guid, id).await?,
+            None => self.database.get_open_poll(chat_guid).await?,
+        };
+        let Some(poll) = poll else {
+            return Ok(CommandResponse::text("❌ There's no open poll to close"));
+        };
+
+        info!("Closing poll #{} in chat {}", poll.id, chat_guid);
+        let reply = polls::close_poll(&self.database, &poll)
+            .await?
+            .unwrap_or_else(|| format!("Poll #{} is already closed", poll.id));
+        Ok(CommandResponse::text(reply))
+    }
+
     async fn handle_character_command(
         &self,
         chat_guid: &str,
+        sender: Option<&str>,
         description: &str,
         config: &mut ChatConfig,
-    ) -> Result<Option<String>> {
+    ) -> Result<CommandResponse> {
         info!("Handling character command for chat {}: {}", chat_guid, description);
 
         // Generate character prompt using AI
         let character_prompt = match self.ai_clients.generate_character_prompt(description).await {
-            Ok(prompt) => prompt,
+            Ok(completion) => {
+                self.usage
+                    .record_completion(chat_guid, sender, "character", &completion)
+                    .await;
+                completion.text
+            }
             Err(e) => {
-                return Ok(Some(format!(
+                return Ok(CommandResponse::text(format!(
                     "❌ Failed to generate character prompt: {}",
                     e
                 )));
             }
         };
 
-        info!("Generated character prompt: {}", &character_prompt[..100.min(character_prompt.len())]);
+        info!("Generated character prompt: {}", character_prompt.chars().take(100).collect::<String>());
 
+        self.change_character(
+            chat_guid,
+            Some(character_prompt),
+            HistoryUpdate::Remember,
+            config,
+            format!("✅ Character updated! I'm now: {}", description),
+        )
+        .await
+    }
+
+    /// Switches the chat to a new character prompt, updates the undo history
+    /// once the prompt is saved, and starts a fresh conversation.
+    async fn change_character(
+        &self,
+        chat_guid: &str,
+        prompt: Option<String>,
+        history: HistoryUpdate,
+        config: &mut ChatConfig,
+        reply: String,
+    ) -> Result<CommandResponse> {
         // Update chat config
-        config.character_prompt = Some(character_prompt);
+        let previous = std::mem::replace(&mut config.character_prompt, prompt);
         config.updated_at = Utc::now();
 
-        // Save to database
+        // Save to database, and only then touch the undo history
         if let Err(e) = self.database.save_chat_config(config).await {
-            return Ok(Some(format!(
+            config.character_prompt = previous;
+            return Ok(CommandResponse::text(format!(
                 "❌ Failed to save character config: {}",
                 e
             )));
         }
+        match history {
+            HistoryUpdate::Remember => {
+                self.database
+                    .push_character_prompt_history(chat_guid, previous.as_deref())
+                    .await?
+            }
+            HistoryUpdate::Restored(id) => self.database.delete_character_prompt_history(id).await?,
+        }
 
         // Clear chat context since we're switching characters
-        // This will be handled by the caller
+        self.database.clear_chat_context(chat_guid).await?;
+
+        Ok(CommandResponse::with_context(reply, ContextChange::Clear))
+    }
+
+    async fn handle_preview_character(
+        &self,
+        chat_guid: &str,
+        sender: Option<&str>,
+        description: &str,
+    ) -> Result<CommandResponse> {
+        info!("Previewing character for chat {}: {}", chat_guid, description);
+
+        match self.ai_clients.generate_character_prompt(description).await {
+            Ok(completion) => {
+                self.usage
+                    .record_completion(chat_guid, sender, "character", &completion)
+                    .await;
+                Ok(CommandResponse::text(format!(
+                    "{}\n\nTo use this, say @character raw followed by the prompt (edit it first if you like)",
+                    completion.text
+                )))
+            }
+            Err(e) => Ok(CommandResponse::text(format!(
+                "❌ Failed to generate character prompt: {}",
+                e
+            ))),
+        }
+    }
+
+    async fn handle_undo_character(&self, chat_guid: &str, config: &mut ChatConfig) -> Result<CommandResponse> {
+        info!("Undoing character change for chat {}", chat_guid);
+
+        let Some((id, previous)) = self.database.latest_character_prompt_history(chat_guid).await? else {
+            return Ok(CommandResponse::text("❌ No earlier character to go back to"));
+        };
+
+        let reply = if previous.is_some() {
+            "✅ Restored the previous character"
+        } else {
+            "✅ Restored the default character"
+        };
+        self.change_character(chat_guid, previous, HistoryUpdate::Restored(id), config, reply.to_string())
+            .await
+    }
+
+    async fn handle_save_persona(
+        &self,
+        chat_guid: &str,
+        name: &str,
+        config: &ChatConfig,
+    ) -> Result<CommandResponse> {
+        info!("Saving persona for chat {}: {}", chat_guid, name);
+
+        if !is_valid_persona_name(name) {
+            return Ok(CommandResponse::text(
+                "❌ Persona names must be 1-32 letters, numbers, - or _",
+            ));
+        }
+
+        let Some(prompt) = &config.character_prompt else {
+            return Ok(CommandResponse::text(
+                "❌ There's no character to save yet. Use @character <description> first",
+            ));
+        };
+
+        self.database.save_persona(chat_guid, name, prompt).await?;
+
+        Ok(CommandResponse::text(format!(
+            "✅ Saved this character as '{}'. Say @character use {} to bring it back",
+            name, name
+        )))
+    }
+
+    async fn handle_use_persona(
+        &self,
+        chat_guid: &str,
+        name: &str,
+        config: &mut ChatConfig,
+    ) -> Result<CommandResponse> {
+        info!("Switching chat {} to persona {}", chat_guid, name);
+
+        let Some(persona) = self.database.find_persona(chat_guid, name).await? else {
+            return Ok(CommandResponse::text(format!(
+                "❌ No persona named '{}'. Say @character list to see saved ones",
+                name
+            )));
+        };
+
+        self.change_character(
+            chat_guid,
+            Some(persona.prompt),
+            HistoryUpdate::Remember,
+            config,
+            format!("✅ Character switched to '{}'", persona.name),
+        )
+        .await
+    }
+
+    async fn handle_share_persona(&self, chat_guid: &str, name: &str) -> Result<CommandResponse> {
+        info!("Sharing persona for chat {}: {}", chat_guid, name);
+
+        if !self.database.share_persona(chat_guid, name).await? {
+            return Ok(CommandResponse::text(format!(
+                "❌ This chat has no persona named '{}'",
+                name
+            )));
+        }
 
-        Ok(Some(format!(
-            "✅ Character updated! I'm now: {}",
-            description
+        Ok(CommandResponse::text(format!(
+            "✅ '{}' can now be used from any chat",
+            name
         )))
     }
 
+    async fn handle_list_personas(&self, chat_guid: &str) -> Result<CommandResponse> {
+        let personas = self.database.list_personas(chat_guid).await?;
+        if personas.is_empty() {
+            return Ok(CommandResponse::text(
+                "No saved personas yet. Say @character save <name> to save the current one",
+            ));
+        }
+
+        let mut lines = vec!["Saved personas:".to_string()];
+        lines.extend(personas.iter().map(|persona| {
+            if persona.chat_guid != chat_guid {
+                format!("• {} (shared)", persona.name)
+            } else if persona.shared {
+                format!("• {} (shared with other chats)", persona.name)
+            } else {
+                format!("• {}", persona.name)
+            }
+        }));
+
+        Ok(CommandResponse::text(lines.join("\n")))
+    }
+
+    async fn handle_show_persona(
+        &self,
+        chat_guid: &str,
+        name: Option<&str>,
+        config: &ChatConfig,
+    ) -> Result<CommandResponse> {
+        let prompt = match name {
+            Some(name) => match self.database.find_persona(chat_guid, name).await? {
+                Some(persona) => persona.prompt,
+                None => {
+                    return Ok(CommandResponse::text(format!(
+                        "❌ No persona named '{}'",
+                        name
+                    )))
+                }
+            },
+            None => match &config.character_prompt {
+                Some(prompt) => prompt.clone(),
+                None => return Ok(CommandResponse::text("Using the default character")),
+            },
+        };
+
+        Ok(CommandResponse::text(prompt))
+    }
+
     async fn handle_unhinge_command(
         &self,
         chat_guid: &str,
         enabled: bool,
         config: &mut ChatConfig,
-    ) -> Result<Option<String>> {
+    ) -> Result<CommandResponse> {
         info!("Handling unhinge command for chat {}: {}", chat_guid, enabled);
 
         // Update chat config
@@ -155,14 +1290,14 @@ impl CommandHandler {
 
         // Save to database
         if let Err(e) = self.database.save_chat_config(config).await {
-            return Ok(Some(format!(
+            return Ok(CommandResponse::text(format!(
                 "❌ Failed to save unhinge config: {}",
                 e
             )));
         }
 
         let status = if enabled { "enabled" } else { "disabled" };
-        Ok(Some(format!(
+        Ok(CommandResponse::text(format!(
             "✅ Unhinge mode {}",
             status
         )))
@@ -173,20 +1308,20 @@ impl CommandHandler {
         chat_guid: &str,
         trigger_name: &str,
         config: &mut ChatConfig,
-    ) -> Result<Option<String>> {
+    ) -> Result<CommandResponse> {
         info!("Handling name command for chat {}: {}", chat_guid, trigger_name);
 
         // Validate trigger name (alphanumeric only, 1-20 characters)
         if trigger_name.len() > 20 || trigger_name.is_empty() {
-            return Ok(Some(format!(
-                "❌ Trigger name must be 1-20 characters long"
-            )));
+            return Ok(CommandResponse::text(
+                "❌ Trigger name must be 1-20 characters long".to_string()
+            ));
         }
 
         if !trigger_name.chars().all(|c| c.is_alphanumeric()) {
-            return Ok(Some(format!(
-                "❌ Trigger name must contain only letters and numbers"
-            )));
+            return Ok(CommandResponse::text(
+                "❌ Trigger name must contain only letters and numbers".to_string()
+            ));
         }
 
         let old_name = config.trigger_name.clone();
@@ -197,17 +1332,280 @@ impl CommandHandler {
 
         // Save to database
         if let Err(e) = self.database.save_chat_config(config).await {
-            return Ok(Some(format!(
+            return Ok(CommandResponse::text(format!(
                 "❌ Failed to save trigger name: {}",
                 e
             )));
         }
 
-        Ok(Some(format!(
+        Ok(CommandResponse::text(format!(
             "✅ Trigger name changed from '{}' to '{}'. You can now say '{}, hello!' instead of using @",
             old_name, trigger_name, trigger_name
         )))
     }
+
+    async fn handle_voice_command(
+        &self,
+        chat_guid: &str,
+        mode: ReplyMode,
+        voice: Option<String>,
+        config: &mut ChatConfig,
+    ) -> Result<CommandResponse> {
+        info!("Handling voice command for chat {}: {:?} {:?}", chat_guid, mode, voice);
+
+        if let Some(voice) = &voice {
+            if voice.len() > 32
+                || !voice.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+            {
+                return Ok(CommandResponse::text("❌ Voice names can only contain letters, numbers, - and _".to_string()));
+            }
+        }
+
+        // Update chat config
+        config.reply_mode = mode;
+        if voice.is_some() {
+            config.voice = voice;
+        }
+        config.updated_at = Utc::now();
+
+        // Save to database
+        if let Err(e) = self.database.save_chat_config(config).await {
+            return Ok(CommandResponse::text(format!(
+                "❌ Failed to save voice config: {}",
+                e
+            )));
+        }
+
+        let voice_name = config.voice.as_deref().unwrap_or("default");
+        Ok(CommandResponse::text(match mode {
+            ReplyMode::Text => "✅ Voice replies off".to_string(),
+            ReplyMode::Voice => format!("✅ Voice replies on (voice: {})", voice_name),
+            ReplyMode::VoiceAndText => format!("✅ Voice and text replies on (voice: {})", voice_name),
+        }))
+    }
+
+    fn describe_config(&self, config: &ChatConfig) -> String {
+        let character = match &config.character_prompt {
+            Some(prompt) if prompt.chars().count() > PROMPT_PREVIEW_CHARS => format!(
+                "{}…",
+                prompt.chars().take(PROMPT_PREVIEW_CHARS).collect::<String>()
+            ),
+            Some(prompt) => prompt.clone(),
+            None => "default".to_string(),
+        };
+        let provider = if config.use_ollama { "Ollama (unhinged)" } else { "OpenAI" };
+        let model = match config.model() {
+            Some(model) => model.to_string(),
+            None => format!("default ({})", self.ai_clients.default_model(config.use_ollama)),
+        };
+
+        [
+            "Chat settings:".to_string(),
+            format!("• Name: {}", config.trigger_name),
+            format!("• Character: {}", character),
+            format!("• Provider: {}", provider),
+            format!("• Proactive: {}", if config.proactive { "on" } else { "off" }),
+            format!("• model: {}", model),
+            format!("• temperature: {}", config.temperature),
+            format!(
+                "• reply_mode: {} (voice: {})",
+                config.reply_mode.as_str(),
+                config.voice.as_deref().unwrap_or("default")
+            ),
+            format!("• context_size: {} messages", config.context_size),
+            if config.follow_up_secs > 0 {
+                format!("• follow_up: {} seconds", config.follow_up_secs)
+            } else {
+                "• follow_up: off".to_string()
+            },
+            format!("• intent_threshold: {}", config.intent_threshold),
+            match &config.timezone {
+                Some(timezone) => format!("• timezone: {}", timezone),
+                None => format!("• timezone: default ({})", self.default_timezone),
+            },
+        ]
+        .join("\n")
+    }
+
+    async fn handle_set_config_command(
+        &self,
+        chat_guid: &str,
+        key: &str,
+        value: &str,
+        config: &mut ChatConfig,
+    ) -> Result<CommandResponse> {
+        info!("Handling config set for chat {}: {} = {}", chat_guid, key, value);
+
+        if let Err(message) = apply_setting(config, key, value) {
+            return Ok(CommandResponse::text(format!("❌ {}", message)));
+        }
+        config.updated_at = Utc::now();
+
+        // Save to database
+        if let Err(e) = self.database.save_chat_config(config).await {
+            return Ok(CommandResponse::text(format!(
+                "❌ Failed to save config: {}",
+                e
+            )));
+        }
+
+        Ok(CommandResponse::text(format!("✅ {} set to {}", key, value)))
+    }
+
+    async fn handle_reset_command(&self, chat_guid: &str) -> Result<CommandResponse> {
+        info!("Handling reset command for chat {}", chat_guid);
+
+        let removed = self.database.clear_chat_context(chat_guid).await?;
+
+        Ok(CommandResponse::with_context(
+            format!("✅ Conversation reset. Forgot {} stored messages.", removed),
+            ContextChange::Clear,
+        ))
+    }
+
+    async fn handle_forget_command(&self, chat_guid: &str, count: usize) -> Result<CommandResponse> {
+        info!("Handling forget command for chat {}: {}", chat_guid, count);
+
+        if count == 0 || count > MAX_FORGET_COUNT {
+            return Ok(CommandResponse::text(format!(
+                "❌ I can forget between 1 and {} messages at a time",
+                MAX_FORGET_COUNT
+            )));
+        }
+
+        let removed = self
+            .database
+            .delete_last_messages(chat_guid, count as i64)
+            .await?;
+
+        let noun = if removed == 1 { "message" } else { "messages" };
+        Ok(CommandResponse::with_context(
+            format!("✅ Forgot the last {} {}", removed, noun),
+            ContextChange::ForgetLast(count),
+        ))
+    }
+}
+
+fn describe_triggers(config: &ChatConfig) -> String {
+    let mut lines = vec![format!("Name: {}", config.trigger_name)];
+    if config.triggers.is_empty() {
+        lines.push("No custom triggers. Say @trigger add <word> to add one".to_string());
+    } else {
+        lines.push("Custom triggers:".to_string());
+        lines.extend(config.triggers.iter().map(|trigger| format!("• {}", trigger)));
+    }
+    lines.join("\n")
+}
+
+/// Normalizes a sender handle so the same person always compares equal:
+/// emails are lowercased and phone numbers lose their formatting.
+pub fn normalize_handle(handle: &str) -> String {
+    let handle = handle.trim().to_lowercase();
+    if handle.contains('@') {
+        return handle;
+    }
+
+    handle
+        .chars()
+        .filter(|c| c.is_ascii_digit() || *c == '+')
+        .collect()
+}
+
+fn is_valid_persona_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 32
+        && name.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Validates and applies a single `@config set` change.
+fn apply_setting(config: &mut ChatConfig, key: &str, value: &str) -> Result<(), String> {
+    match key {
+        "model" => {
+            if value.eq_ignore_ascii_case("default") {
+                config.set_model(None);
+            } else if value.len() > 64
+                || !value
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | '.' | ':' | '/'))
+            {
+                return Err("Model names can only contain letters, numbers and - _ . : /".to_string());
+            } else {
+                config.set_model(Some(value.to_string()));
+            }
+        }
+        "temperature" => match value.parse::<f32>() {
+            Ok(temperature) if (0.0..=2.0).contains(&temperature) => config.temperature = temperature,
+            _ => return Err("Temperature must be a number between 0 and 2".to_string()),
+        },
+        "reply_mode" => match ReplyMode::parse(value) {
+            Some(mode) => config.reply_mode = mode,
+            None => return Err("Reply mode must be text, voice or both".to_string()),
+        },
+        "context_size" => match value.parse::<usize>() {
+            Ok(size) if (1..=MAX_CONTEXT_SIZE).contains(&size) => config.context_size = size,
+            _ => {
+                return Err(format!(
+                    "Context size must be between 1 and {} messages",
+                    MAX_CONTEXT_SIZE
+                ))
+            }
+        },
+        "follow_up" => match value.parse::<u64>() {
+            Ok(secs) if secs <= MAX_FOLLOW_UP_SECS => config.follow_up_secs = secs,
+            _ if value.eq_ignore_ascii_case("off") => config.follow_up_secs = 0,
+            _ => {
+                return Err(format!(
+                    "Follow-up window must be 0-{} seconds (0 or off disables it)",
+                    MAX_FOLLOW_UP_SECS
+                ))
+            }
+        },
+        "intent_threshold" => match value.parse::<f32>() {
+            Ok(threshold) if (0.0..=1.0).contains(&threshold) => config.intent_threshold = threshold,
+            _ => return Err("Intent threshold must be a number between 0 and 1".to_string()),
+        },
+        "timezone" => {
+            if value.eq_ignore_ascii_case("default") {
+                config.timezone = None;
+            } else {
+                let tz = parse_timezone(value)
+                    .ok_or_else(|| "Time zones look like America/New_York or Europe/London".to_string())?;
+                config.timezone = Some(tz.name().to_string());
+            }
+        }
+        _ => {
+            return Err(format!(
+                "Unknown setting '{}'. Try model, temperature, reply_mode, context_size, follow_up, intent_threshold or timezone",
+                key
+            ))
+        }
+    }
+
+    Ok(())
+}
+
+fn help_text(command: Option<&str>) -> String {
+    match command {
+        Some(name) => match find_command(name) {
+            Some(spec) => format!(
+                "{}\n{}\nWho can use it: {}",
+                spec.syntax,
+                spec.description,
+                spec.permission.describe()
+            ),
+            None => format!("❌ Unknown command '{}'. Say @help to see them all.", name),
+        },
+        None => {
+            let mut lines = vec!["Commands:".to_string()];
+            lines.extend(
+                COMMANDS
+                    .iter()
+                    .map(|spec| format!("• {} — {}", spec.syntax, spec.description)),
+            );
+            lines.push("Say @help <command> for details.".to_string());
+            lines.join("\n")
+        }
+    }
 }
 
 #[cfg(test)]
@@ -226,6 +1624,41 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_persona_command_parsing() {
+        let parser = CommandParser::new().unwrap();
+
+        let cmd = parser.parse_command("@character save Pirate");
+        assert!(matches!(cmd, Some(Command::SavePersona { ref name }) if name == "pirate"));
+
+        let cmd = parser.parse_command("@character use pirate");
+        assert!(matches!(cmd, Some(Command::UsePersona { ref name }) if name == "pirate"));
+
+        assert!(matches!(parser.parse_command("@character list"), Some(Command::ListPersonas)));
+        assert!(matches!(
+            parser.parse_command("@character show"),
+            Some(Command::ShowPersona { name: None })
+        ));
+
+        let cmd = parser.parse_command("@character raw You are a pirate.\nSpeak in rhymes.");
+        assert!(matches!(cmd, Some(Command::RawCharacter { ref prompt }) if prompt == "You are a pirate.\nSpeak in rhymes."));
+
+        let cmd = parser.parse_command("@character preview a sleepy owl");
+        assert!(matches!(cmd, Some(Command::PreviewCharacter { ref description }) if description == "a sleepy owl"));
+
+        assert!(matches!(parser.parse_command("@character undo"), Some(Command::UndoCharacter)));
+
+        // Bare subcommands get a usage message instead of becoming a character
+        let cmd = parser.parse_command("@character save");
+        assert!(matches!(cmd, Some(Command::CharacterUsage { usage: "@character save <name>" })));
+        let cmd = parser.parse_command("@character RAW ");
+        assert!(matches!(cmd, Some(Command::CharacterUsage { usage: "@character raw <prompt>" })));
+
+        // Longer text is still a character description
+        let cmd = parser.parse_command("@character used car salesman");
+        assert!(matches!(cmd, Some(Command::Character { ref description }) if description == "used car salesman"));
+    }
+
     #[test]
     fn test_unhinge_command_parsing() {
         let parser = CommandParser::new().unwrap();
@@ -262,6 +1695,242 @@ mod tests {
         assert!(cmd.is_none());
     }
 
+    #[test]
+    fn test_voice_command_parsing() {
+        let parser = CommandParser::new().unwrap();
+
+        let cmd = parser.parse_command("@voice on nova");
+        assert!(matches!(cmd, Some(Command::Voice { mode: ReplyMode::Voice, voice: Some(ref v) }) if v == "nova"));
+
+        let cmd = parser.parse_command("@voice both");
+        assert!(matches!(cmd, Some(Command::Voice { mode: ReplyMode::VoiceAndText, voice: None })));
+
+        let cmd = parser.parse_command("@voice off");
+        assert!(matches!(cmd, Some(Command::Voice { mode: ReplyMode::Text, .. })));
+
+        let cmd = parser.parse_command("@voice loud");
+        assert!(cmd.is_none());
+    }
+
+    #[test]
+    fn test_reset_and_forget_parsing() {
+        let parser = CommandParser::new().unwrap();
+
+        assert!(matches!(parser.parse_command("@reset"), Some(Command::Reset)));
+        assert!(parser.parse_command("@reset everything please").is_none());
+
+        let cmd = parser.parse_command("@forget last 3");
+        assert!(matches!(cmd, Some(Command::Forget { count: 3 })));
+
+        let cmd = parser.parse_command("@forget last");
+        assert!(matches!(cmd, Some(Command::Forget { count: 1 })));
+
+        assert!(parser.parse_command("@forget about it").is_none());
+    }
+
+    #[test]
+    fn test_config_command_parsing() {
+        let parser = CommandParser::new().unwrap();
+
+        assert!(matches!(parser.parse_command("@config"), Some(Command::ShowConfig)));
+        assert!(matches!(parser.parse_command("@config show"), Some(Command::ShowConfig)));
+
+        let cmd = parser.parse_command("@config set Context-Size 20");
+        assert!(matches!(
+            cmd,
+            Some(Command::SetConfig { ref key, ref value }) if key == "context_size" && value == "20"
+        ));
+
+        assert!(parser.parse_command("@config please").is_none());
+    }
+
+    #[test]
+    fn test_apply_setting_validation() {
+        let mut config = ChatConfig::new("chat".to_string());
+
+        assert!(apply_setting(&mut config, "temperature", "1.2").is_ok());
+        assert_eq!(config.temperature, 1.2);
+        assert!(apply_setting(&mut config, "temperature", "3").is_err());
+
+        assert!(apply_setting(&mut config, "model", "gpt-4o-mini").is_ok());
+        assert_eq!(config.model(), Some("gpt-4o-mini"));
+        // Switching provider doesn't carry the OpenAI model over to Ollama
+        config.use_ollama = true;
+        assert_eq!(config.model(), None);
+        assert!(apply_setting(&mut config, "model", "llama3").is_ok());
+        config.use_ollama = false;
+        assert_eq!(config.model(), Some("gpt-4o-mini"));
+        assert!(apply_setting(&mut config, "model", "default").is_ok());
+        assert!(config.model().is_none());
+        assert_eq!(config.ollama_model.as_deref(), Some("llama3"));
+
+        assert!(apply_setting(&mut config, "context_size", "0").is_err());
+        assert!(apply_setting(&mut config, "reply_mode", "both").is_ok());
+        assert_eq!(config.reply_mode, ReplyMode::VoiceAndText);
+        assert!(apply_setting(&mut config, "follow_up", "120").is_ok());
+        assert_eq!(config.follow_up_secs, 120);
+        assert!(apply_setting(&mut config, "follow_up", "off").is_ok());
+        assert_eq!(config.follow_up_secs, 0);
+        assert!(apply_setting(&mut config, "follow_up", "9000").is_err());
+        assert!(apply_setting(&mut config, "intent_threshold", "0.8").is_ok());
+        assert!(apply_setting(&mut config, "intent_threshold", "1.5").is_err());
+        assert!(apply_setting(&mut config, "timezone", "america/new_york").is_ok());
+        assert_eq!(config.timezone.as_deref(), Some("America/New_York"));
+        assert!(apply_setting(&mut config, "timezone", "Mars/Base").is_err());
+        assert!(apply_setting(&mut config, "timezone", "default").is_ok());
+        assert!(config.timezone.is_none());
+        assert!(apply_setting(&mut config, "colour", "blue").is_err());
+    }
+
+    #[test]
+    fn test_admin_command_parsing() {
+        let parser = CommandParser::new().unwrap();
+
+        let cmd = parser.parse_command("@admin add +1 (555) 123-4567");
+        assert!(matches!(cmd, Some(Command::AddOwner { ref handle }) if handle == "+1 (555) 123-4567"));
+
+        let cmd = parser.parse_command("@admin remove Friend@Example.com");
+        assert!(matches!(cmd, Some(Command::RemoveOwner { .. })));
+
+        assert!(matches!(parser.parse_command("@admin list"), Some(Command::ListOwners)));
+        assert!(matches!(parser.parse_command("@admin claim"), Some(Command::ClaimOwner)));
+        assert_eq!(Command::ClaimOwner.required_permission(), PermissionLevel::Everyone);
+        assert!(parser.parse_command("@admin add").is_none());
+        assert!(parser.parse_command("@admin claim +15551234567").is_none());
+    }
+
+    #[test]
+    fn test_trigger_command_parsing() {
+        let parser = CommandParser::new().unwrap();
+
+        let cmd = parser.parse_command("@trigger add hey bot");
+        assert!(matches!(cmd, Some(Command::AddTrigger { ref trigger }) if trigger == "hey bot"));
+
+        let cmd = parser.parse_command("@trigger remove /^yo/");
+        assert!(matches!(cmd, Some(Command::RemoveTrigger { ref trigger }) if trigger == "/^yo/"));
+
+        assert!(matches!(parser.parse_command("@trigger list"), Some(Command::ListTriggers)));
+        assert!(parser.parse_command("@trigger add").is_none());
+    }
+
+    #[test]
+    fn test_bot_command_parsing() {
+        let parser = CommandParser::new().unwrap();
+
+        assert!(matches!(parser.parse_command("@bot off"), Some(Command::Bot { enabled: false })));
+        assert!(matches!(parser.parse_command("@bot on"), Some(Command::Bot { enabled: true })));
+        assert!(parser.parse_command("@bot please stop").is_none());
+
+        let cmd = parser.parse_command("@proactive on");
+        assert!(matches!(cmd, Some(Command::Proactive { enabled: true })));
+    }
+
+    #[test]
+    fn test_schedule_command_parsing() {
+        let parser = CommandParser::new().unwrap();
+
+        let cmd = parser.parse_command("@schedule add every weekday 8:30am: motivational quote in character");
+        assert!(matches!(
+            cmd,
+            Some(Command::AddSchedule { ref when, ref prompt })
+                if when == "every weekday 8:30am" && prompt == "motivational quote in character"
+        ));
+
+        let cmd = parser.parse_command("@schedule remove #4");
+        assert!(matches!(cmd, Some(Command::RemoveSchedule { id: 4 })));
+        assert!(matches!(parser.parse_command("@schedule list"), Some(Command::ListSchedules)));
+
+        assert!(parser.parse_command("@schedule add every day 9am").is_none());
+        assert!(parser.parse_command("@schedule add sunday 6pm:   ").is_none());
+    }
+
+    #[test]
+    fn test_poll_command_parsing() {
+        let parser = CommandParser::new().unwrap();
+
+        let cmd = parser.parse_command(r#"@poll "Where should we eat?" tacos | sushi | pizza"#);
+        assert!(matches!(
+            cmd,
+            Some(Command::CreatePoll { ref question, ref options })
+                if question == "Where should we eat?" && options.len() == 3
+        ));
+
+        assert!(matches!(parser.parse_command("@poll close"), Some(Command::ClosePoll { id: None })));
+        let cmd = parser.parse_command("@poll close #3");
+        assert!(matches!(cmd, Some(Command::ClosePoll { id: Some(3) })));
+        assert!(parser.parse_command("@poll close the door").is_none());
+    }
+
+    #[test]
+    fn test_usage_command_parsing() {
+        let parser = CommandParser::new().unwrap();
+
+        assert!(matches!(parser.parse_command("@usage"), Some(Command::Usage { week: false })));
+        assert!(matches!(parser.parse_command("@usage Week"), Some(Command::Usage { week: true })));
+        assert!(parser.parse_command("@usage forever").is_none());
+    }
+
+    #[test]
+    fn test_reminders_command_parsing() {
+        let parser = CommandParser::new().unwrap();
+
+        assert!(matches!(parser.parse_command("@reminders"), Some(Command::ListReminders)));
+        let cmd = parser.parse_command("@reminders cancel #12");
+        assert!(matches!(cmd, Some(Command::CancelReminder { id: 12 })));
+        assert!(parser.parse_command("@reminders cancel soon").is_none());
+    }
+
+    #[test]
+    fn test_required_permissions() {
+        let set = Command::SetConfig { key: "model".to_string(), value: "x".to_string() };
+        assert_eq!(set.required_permission(), PermissionLevel::Owner);
+        assert_eq!(Command::ShowConfig.required_permission(), PermissionLevel::Everyone);
+        assert_eq!(Command::Help { command: None }.required_permission(), PermissionLevel::Everyone);
+        let preview = Command::PreviewCharacter { description: "owl".to_string() };
+        assert_eq!(preview.required_permission(), PermissionLevel::Owner);
+
+        assert_eq!(normalize_handle(" +1 (555) 123-4567 "), "+15551234567");
+        assert_eq!(normalize_handle("Friend@Example.com"), "friend@example.com");
+    }
+
+    #[test]
+    fn test_help_command_parsing() {
+        let parser = CommandParser::new().unwrap();
+
+        let cmd = parser.parse_command("@help");
+        assert!(matches!(cmd, Some(Command::Help { command: None })));
+
+        let cmd = parser.parse_command("@help @Character");
+        assert!(matches!(cmd, Some(Command::Help { command: Some(ref c) }) if c == "character"));
+
+        let cmd = parser.parse_command("@helpful tips");
+        assert!(cmd.is_none());
+    }
+
+    #[test]
+    fn test_help_text_covers_registry() {
+        let text = help_text(None);
+        for spec in COMMANDS {
+            assert!(text.contains(spec.syntax));
+        }
+
+        assert!(help_text(Some("voice")).contains("@voice <on|off|both> [voice]"));
+        assert!(help_text(Some("nope")).starts_with("❌"));
+    }
+
+    #[test]
+    fn test_commands_must_start_the_message() {
+        let parser = CommandParser::new().unwrap();
+
+        assert!(parser.parse_command("lol did you see @character pirate").is_none());
+        assert!(parser.parse_command("can you @help me with this").is_none());
+        assert!(parser.parse_command("@help me write a poem").is_none());
+
+        let cmd = parser.parse_command("  @Character a witty robot");
+        assert!(matches!(cmd, Some(Command::Character { ref description }) if description == "a witty robot"));
+        assert!(matches!(parser.parse_command("@Bot ON"), Some(Command::Bot { enabled: true })));
+    }
+
     #[test]
     fn test_no_command() {
         let parser = CommandParser::new().unwrap();
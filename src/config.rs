@@ -1,3 +1,6 @@
+use crate::commands::{normalize_handle, COMMANDS};
+use crate::limits::Quota;
+use crate::scheduling::parse_timezone;
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use std::env;
@@ -11,6 +14,109 @@ pub struct Config {
     pub bot_trigger: String,
     pub ollama_model: String,
     pub database_url: String,
+    pub max_attachment_bytes: i64,
+    pub vision_max_dimension: u32,
+    pub image_convert_command: Option<String>,
+    pub max_vision_images: usize,
+    pub vision_context_images: bool,
+    pub attachment_cache_dir: String,
+    pub attachment_cache_max_bytes: i64,
+    pub stt_api: String,
+    pub stt_api_key: Option<String>,
+    pub stt_model: String,
+    pub audio_convert_command: Option<String>,
+    pub tts_api: String,
+    pub tts_api_key: Option<String>,
+    pub tts_model: String,
+    pub tts_voice: String,
+    pub admin_handles: Vec<String>,
+    pub allowed_chats: Vec<String>,
+    pub denied_chats: Vec<String>,
+    pub allowed_handles: Vec<String>,
+    pub denied_handles: Vec<String>,
+    pub dm_policy: ChatPolicy,
+    pub group_policy: ChatPolicy,
+    pub intent_classifier: IntentClassifier,
+    pub intent_model: Option<String>,
+    pub proactive_interval_mins: u64,
+    pub proactive_min_messages: usize,
+    pub quiet_hours: Option<QuietHours>,
+    pub poll_timeout_hours: i64,
+    pub sender_messages_per_minute: u32,
+    pub chat_messages_per_minute: u32,
+    pub chat_daily_quota: Quota,
+    pub sender_daily_quota: Quota,
+    pub model_prices: String,
+    pub image_price: f64,
+    pub default_timezone: String,
+    pub metrics_addr: Option<String>,
+}
+
+/// Hours of the day (chat time) when the bot doesn't speak up on its own,
+/// written as "22-8". The range wraps past midnight.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuietHours {
+    pub start: u32,
+    pub end: u32,
+}
+
+impl QuietHours {
+    pub fn parse(value: &str) -> Option<Self> {
+        let (start, end) = value.trim().split_once('-')?;
+        let start = start.trim().parse().ok().filter(|h| *h < 24)?;
+        let end = end.trim().parse().ok().filter(|h| *h < 24)?;
+        Some(Self { start, end })
+    }
+
+    pub fn contains(&self, hour: u32) -> bool {
+        if self.start <= self.end {
+            (self.start..self.end).contains(&hour)
+        } else {
+            hour >= self.start || hour < self.end
+        }
+    }
+}
+
+/// Which model, if any, decides whether a passing mention of the bot's name
+/// is actually addressed to it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum IntentClassifier {
+    Off,
+    OpenAI,
+    Ollama,
+}
+
+impl IntentClassifier {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "off" | "" => Some(IntentClassifier::Off),
+            "openai" => Some(IntentClassifier::OpenAI),
+            "ollama" => Some(IntentClassifier::Ollama),
+            _ => None,
+        }
+    }
+}
+
+/// When the bot answers in a kind of chat.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ChatPolicy {
+    /// Reply to every message
+    Always,
+    /// Reply only to triggered messages and commands
+    Trigger,
+    /// Ignore the chat entirely
+    Off,
+}
+
+impl ChatPolicy {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "always" => Some(ChatPolicy::Always),
+            "trigger" => Some(ChatPolicy::Trigger),
+            "off" => Some(ChatPolicy::Off),
+            _ => None,
+        }
+    }
 }
 
 impl Config {
@@ -25,6 +131,112 @@ impl Config {
             bot_trigger: env::var("BOT_TRIGGER").unwrap_or_else(|_| "@ava".to_string()),
             ollama_model: env::var("OLLAMA_MODEL").unwrap_or_else(|_| "llama3.2".to_string()),
             database_url: env::var("DATABASE_URL").unwrap_or_else(|_| "sqlite:./bot.db".to_string()),
+            max_attachment_bytes: env::var("MAX_ATTACHMENT_BYTES")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(20 * 1024 * 1024),
+            vision_max_dimension: env::var("VISION_MAX_DIMENSION")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(2048),
+            // Used for formats the vision API can't read (HEIC). `sips` ships with macOS.
+            image_convert_command: match env::var("IMAGE_CONVERT_COMMAND") {
+                Ok(command) if command.trim().is_empty() => None,
+                Ok(command) => Some(command),
+                Err(_) => Some("sips -s format jpeg {input} --out {output}".to_string()),
+            },
+            max_vision_images: env::var("MAX_VISION_IMAGES")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(4),
+            vision_context_images: env::var("VISION_CONTEXT_IMAGES")
+                .map(|v| v == "true" || v == "1")
+                .unwrap_or(false),
+            attachment_cache_dir: env::var("ATTACHMENT_CACHE_DIR").unwrap_or_else(|_| "./attachment_cache".to_string()),
+            attachment_cache_max_bytes: env::var("ATTACHMENT_CACHE_MAX_BYTES")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(500 * 1024 * 1024),
+            // Any OpenAI-compatible /audio/transcriptions endpoint, e.g. a local whisper server
+            stt_api: env::var("STT_API").unwrap_or_else(|_| "https://api.openai.com/v1".to_string()),
+            stt_api_key: env::var("STT_API_KEY").ok().or_else(|| env::var("OPENAI_API_KEY").ok()),
+            stt_model: env::var("STT_MODEL").unwrap_or_else(|_| "whisper-1".to_string()),
+            // iMessage voice memos are CAF, which transcription APIs don't accept
+            audio_convert_command: match env::var("AUDIO_CONVERT_COMMAND") {
+                Ok(command) if command.trim().is_empty() => None,
+                Ok(command) => Some(command),
+                Err(_) => Some("afconvert -f m4af -d aac {input} {output}".to_string()),
+            },
+            // Any OpenAI-compatible /audio/speech endpoint
+            tts_api: env::var("TTS_API").unwrap_or_else(|_| "https://api.openai.com/v1".to_string()),
+            tts_api_key: env::var("TTS_API_KEY").ok().or_else(|| env::var("OPENAI_API_KEY").ok()),
+            tts_model: env::var("TTS_MODEL").unwrap_or_else(|_| "gpt-4o-mini-tts".to_string()),
+            tts_voice: env::var("TTS_VOICE").unwrap_or_else(|_| "alloy".to_string()),
+            // Comma-separated phone numbers or emails allowed to run any command anywhere
+            admin_handles: handle_list("ADMIN_HANDLES"),
+            // Empty allowlists allow everything; denylists always win
+            allowed_chats: list_var("ALLOWED_CHATS"),
+            denied_chats: list_var("DENIED_CHATS"),
+            allowed_handles: handle_list("ALLOWED_HANDLES"),
+            denied_handles: handle_list("DENIED_HANDLES"),
+            dm_policy: env::var("DM_POLICY")
+                .ok()
+                .and_then(|v| ChatPolicy::parse(&v))
+                .unwrap_or(ChatPolicy::Trigger),
+            group_policy: env::var("GROUP_POLICY")
+                .ok()
+                .and_then(|v| ChatPolicy::parse(&v))
+                .unwrap_or(ChatPolicy::Trigger),
+            intent_classifier: env::var("INTENT_CLASSIFIER")
+                .ok()
+                .and_then(|v| IntentClassifier::parse(&v))
+                .unwrap_or(IntentClassifier::Off),
+            // Defaults to gpt-4o-mini for OpenAI and OLLAMA_MODEL for Ollama
+            intent_model: env::var("INTENT_MODEL").ok().filter(|v| !v.trim().is_empty()),
+            proactive_interval_mins: env::var("PROACTIVE_INTERVAL_MINS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(30),
+            proactive_min_messages: env::var("PROACTIVE_MIN_MESSAGES")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(3),
+            // Empty disables quiet hours
+            quiet_hours: match env::var("QUIET_HOURS") {
+                Ok(hours) => QuietHours::parse(&hours),
+                Err(_) => Some(QuietHours { start: 22, end: 8 }),
+            },
+            poll_timeout_hours: env::var("POLL_TIMEOUT_HOURS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|hours| *hours > 0)
+                .unwrap_or(24),
+            // 0 turns a rate limit off
+            sender_messages_per_minute: env::var("SENDER_MESSAGES_PER_MINUTE")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(6),
+            chat_messages_per_minute: env::var("CHAT_MESSAGES_PER_MINUTE")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(20),
+            chat_daily_quota: Quota::from_env("CHAT_DAILY_TOKEN_QUOTA", "CHAT_DAILY_SPEND_QUOTA"),
+            sender_daily_quota: Quota::from_env("SENDER_DAILY_TOKEN_QUOTA", "SENDER_DAILY_SPEND_QUOTA"),
+            // Extra or overridden prices per million tokens, e.g. "gpt-4o=2.5/10,llama3=0/0"
+            model_prices: env::var("MODEL_PRICES").unwrap_or_default(),
+            // USD per generated or edited image
+            image_price: env::var("IMAGE_PRICE")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(0.04),
+            // IANA name like "America/New_York"; chats can override it with @config
+            default_timezone: env::var("DEFAULT_TIMEZONE")
+                .or_else(|_| env::var("TZ"))
+                .ok()
+                .filter(|tz| parse_timezone(tz).is_some())
+                .unwrap_or_else(|| "UTC".to_string()),
+            // Address for /metrics and /healthz, e.g. "127.0.0.1:9090"; unset disables them
+            metrics_addr: env::var("METRICS_ADDR").ok().filter(|v| !v.trim().is_empty()),
         };
 
         // Validate that we have at least one AI provider configured
@@ -35,12 +247,83 @@ impl Config {
         Ok(config)
     }
 
+    pub fn chat_allowed(&self, chat_guid: &str) -> bool {
+        (self.allowed_chats.is_empty() || self.allowed_chats.iter().any(|c| c == chat_guid))
+            && !self.denied_chats.iter().any(|c| c == chat_guid)
+    }
+
+    /// Senders we can't identify are only let through when there's no
+    /// handle allowlist.
+    pub fn handle_allowed(&self, handle: Option<&str>) -> bool {
+        let Some(handle) = handle.map(normalize_handle) else {
+            return self.allowed_handles.is_empty();
+        };
+
+        (self.allowed_handles.is_empty() || self.allowed_handles.contains(&handle))
+            && !self.denied_handles.contains(&handle)
+    }
+
+    pub fn chat_policy(&self, chat_guid: &str) -> ChatPolicy {
+        if is_direct_chat(chat_guid) {
+            self.dm_policy
+        } else {
+            self.group_policy
+        }
+    }
+
+    /// Triggers shared by every chat. Commands aren't among them: they only
+    /// count at the start of a message, which the command parser checks.
     pub fn triggers(&self) -> Vec<String> {
-        vec![
-            self.bot_trigger.to_lowercase(),
-            "@character".to_string(),
-            "@unhinge".to_string(),
-            "@name".to_string(),
-        ]
-    }
-}
\ No newline at end of file
+        vec![self.bot_trigger.to_lowercase()]
+    }
+
+    /// What older versions copied into every chat's trigger list.
+    pub fn legacy_triggers(&self) -> Vec<String> {
+        let mut triggers = self.triggers();
+        triggers.extend(COMMANDS.iter().map(|spec| format!("@{}", spec.name)));
+        triggers
+    }
+}
+
+/// One-on-one chats have guids like `iMessage;-;+15551234567`; group chats
+/// use `;+;`.
+pub fn is_direct_chat(chat_guid: &str) -> bool {
+    chat_guid.contains(";-;")
+}
+
+fn list_var(name: &str) -> Vec<String> {
+    env::var(name)
+        .map(|value| {
+            value
+                .split(',')
+                .map(|item| item.trim().to_string())
+                .filter(|item| !item.is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn handle_list(name: &str) -> Vec<String> {
+    list_var(name).iter().map(|handle| normalize_handle(handle)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quiet_hours() {
+        let overnight = QuietHours::parse("22-8").unwrap();
+        assert!(overnight.contains(23));
+        assert!(overnight.contains(3));
+        assert!(!overnight.contains(8));
+        assert!(!overnight.contains(12));
+
+        let afternoon = QuietHours::parse(" 13 - 15 ").unwrap();
+        assert!(afternoon.contains(14));
+        assert!(!afternoon.contains(15));
+
+        assert!(QuietHours::parse("25-3").is_none());
+        assert!(QuietHours::parse("").is_none());
+    }
+}
@@ -1,8 +1,8 @@
 use anyhow::{Result, Context};
-use chrono::Utc;
-use sqlx::{Row, SqlitePool, ConnectOptions};
+use chrono::{DateTime, Utc};
+use sqlx::{Row, SqlitePool};
 use std::{fs, str::FromStr};
-use crate::types::{ChatConfig, Message, MessageRole};
+use crate::types::{BlueBubblesAttachment, CachedAttachment, ChatConfig, Message, MessageRole, Persona, Poll, QueuedMessage, RecurringJob, ReplyMode, ScheduledMessage, SenderUsage, UsageEvent, UsageSummary};
 
 #[derive(Clone)]
 pub struct Database {
@@ -36,6 +36,15 @@ impl Database {
         Ok(db)
     }
 
+    /// Checks the database still answers, for the health endpoint.
+    pub async fn ping(&self) -> Result<()> {
+        sqlx::query("SELECT 1")
+            .execute(&self.pool)
+            .await
+            .context("Database is unreachable")?;
+        Ok(())
+    }
+
     async fn run_migrations(&self) -> Result<()> {
         // Create chat_configs table
         sqlx::query(r#"
@@ -95,6 +104,157 @@ impl Database {
         .await
         .context("Failed to create message_queue table")?;
 
+        // Create attachments table for the on-disk attachment cache
+        sqlx::query(r#"
+            CREATE TABLE IF NOT EXISTS attachments (
+                attachment_guid TEXT PRIMARY KEY,
+                chat_guid TEXT NOT NULL,
+                content_hash TEXT NOT NULL, -- file name in the cache directory
+                mime_type TEXT,
+                filename TEXT,
+                size_bytes INTEGER NOT NULL,
+                source TEXT NOT NULL, -- 'downloaded', 'generated'
+                description TEXT, -- prompt for generated images
+                created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+                last_accessed_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+            )
+        "#)
+        .execute(&self.pool)
+        .await
+        .context("Failed to create attachments table")?;
+
+        // Create personas table for saved character prompts
+        sqlx::query(r#"
+            CREATE TABLE IF NOT EXISTS personas (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                chat_guid TEXT NOT NULL, -- chat that saved the persona
+                name TEXT NOT NULL,
+                prompt TEXT NOT NULL,
+                shared BOOLEAN DEFAULT FALSE,
+                created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+                UNIQUE (chat_guid, name)
+            )
+        "#)
+        .execute(&self.pool)
+        .await
+        .context("Failed to create personas table")?;
+
+        // Create character_prompt_history table so prompt changes can be undone
+        sqlx::query(r#"
+            CREATE TABLE IF NOT EXISTS character_prompt_history (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                chat_guid TEXT NOT NULL,
+                prompt TEXT, -- prompt before the change, NULL for the default character
+                created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+            )
+        "#)
+        .execute(&self.pool)
+        .await
+        .context("Failed to create character_prompt_history table")?;
+
+        // Create chat_roles table for per-chat owners
+        sqlx::query(r#"
+            CREATE TABLE IF NOT EXISTS chat_roles (
+                chat_guid TEXT NOT NULL,
+                handle TEXT NOT NULL, -- normalized phone number or email
+                role TEXT NOT NULL DEFAULT 'owner',
+                created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+                PRIMARY KEY (chat_guid, handle)
+            )
+        "#)
+        .execute(&self.pool)
+        .await
+        .context("Failed to create chat_roles table")?;
+
+        // Create scheduled_messages table for reminders
+        sqlx::query(r#"
+            CREATE TABLE IF NOT EXISTS scheduled_messages (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                chat_guid TEXT NOT NULL,
+                message TEXT NOT NULL,
+                send_at TIMESTAMP NOT NULL, -- UTC
+                status TEXT DEFAULT 'pending', -- 'pending', 'sent', 'cancelled', 'failed'
+                created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+            )
+        "#)
+        .execute(&self.pool)
+        .await
+        .context("Failed to create scheduled_messages table")?;
+
+        // Create recurring_jobs table for scheduled prompts
+        sqlx::query(r#"
+            CREATE TABLE IF NOT EXISTS recurring_jobs (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                chat_guid TEXT NOT NULL,
+                schedule TEXT NOT NULL, -- cron expression, read in the chat's time zone
+                description TEXT NOT NULL,
+                prompt TEXT NOT NULL,
+                next_run_at TIMESTAMP NOT NULL, -- UTC
+                last_run_at TIMESTAMP,
+                created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+            )
+        "#)
+        .execute(&self.pool)
+        .await
+        .context("Failed to create recurring_jobs table")?;
+
+        // Create polls and poll_votes tables
+        sqlx::query(r#"
+            CREATE TABLE IF NOT EXISTS polls (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                chat_guid TEXT NOT NULL,
+                question TEXT NOT NULL,
+                options TEXT NOT NULL, -- JSON array
+                message_guid TEXT, -- the bot's poll message, for tapback votes
+                status TEXT DEFAULT 'open', -- 'open', 'closed'
+                closes_at TIMESTAMP NOT NULL, -- UTC
+                created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+            )
+        "#)
+        .execute(&self.pool)
+        .await
+        .context("Failed to create polls table")?;
+
+        sqlx::query(r#"
+            CREATE TABLE IF NOT EXISTS poll_votes (
+                poll_id INTEGER NOT NULL,
+                voter TEXT NOT NULL,
+                option_index INTEGER NOT NULL,
+                voted_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+                PRIMARY KEY (poll_id, voter),
+                FOREIGN KEY (poll_id) REFERENCES polls (id)
+            )
+        "#)
+        .execute(&self.pool)
+        .await
+        .context("Failed to create poll_votes table")?;
+
+        // Create usage_events table for quotas and cost tracking
+        sqlx::query(r#"
+            CREATE TABLE IF NOT EXISTS usage_events (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                chat_guid TEXT NOT NULL,
+                sender TEXT,
+                provider TEXT,
+                kind TEXT DEFAULT 'chat', -- 'chat', 'intent', 'character', 'image'
+                model TEXT NOT NULL,
+                prompt_tokens INTEGER NOT NULL DEFAULT 0,
+                completion_tokens INTEGER NOT NULL DEFAULT 0,
+                latency_ms INTEGER,
+                image_count INTEGER DEFAULT 0,
+                cost_usd REAL NOT NULL DEFAULT 0,
+                created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+            )
+        "#)
+        .execute(&self.pool)
+        .await
+        .context("Failed to create usage_events table")?;
+
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_usage_events_created_at ON usage_events (created_at)")
+            .execute(&self.pool)
+            .await
+            .context("Failed to create usage_events index")?;
+
         // Migration: Add trigger_name column if it doesn't exist
         sqlx::query(r#"
             ALTER TABLE chat_configs ADD COLUMN trigger_name TEXT DEFAULT 'myai'
@@ -103,12 +263,109 @@ impl Database {
         .await
         .ok(); // Ignore error if column already exists
 
+        // Migration: Track attachments (JSON array) on context and queued messages
+        sqlx::query("ALTER TABLE chat_contexts ADD COLUMN attachments TEXT")
+            .execute(&self.pool)
+            .await
+            .ok();
+
+        sqlx::query("ALTER TABLE message_queue ADD COLUMN attachments TEXT")
+            .execute(&self.pool)
+            .await
+            .ok();
+
+        // Migration: Remember who sent queued messages
+        sqlx::query("ALTER TABLE message_queue ADD COLUMN sender TEXT")
+            .execute(&self.pool)
+            .await
+            .ok();
+
+        // Migration: Retry reminders that failed to send
+        sqlx::query("ALTER TABLE scheduled_messages ADD COLUMN attempts INTEGER DEFAULT 0")
+            .execute(&self.pool)
+            .await
+            .ok();
+
+        sqlx::query("ALTER TABLE scheduled_messages ADD COLUMN retry_at TIMESTAMP")
+            .execute(&self.pool)
+            .await
+            .ok();
+
+        // Migration: Flag queued messages that only mention the bot
+        sqlx::query("ALTER TABLE message_queue ADD COLUMN mention BOOLEAN DEFAULT FALSE")
+            .execute(&self.pool)
+            .await
+            .ok();
+
+        // Migration: Voice reply settings
+        sqlx::query("ALTER TABLE chat_configs ADD COLUMN reply_mode TEXT DEFAULT 'text'")
+            .execute(&self.pool)
+            .await
+            .ok();
+
+        sqlx::query("ALTER TABLE chat_configs ADD COLUMN voice TEXT")
+            .execute(&self.pool)
+            .await
+            .ok();
+
+        // Migration: Per-chat model settings
+        sqlx::query("ALTER TABLE chat_configs ADD COLUMN model TEXT")
+            .execute(&self.pool)
+            .await
+            .ok();
+
+        // The `model` column holds the OpenAI override; Ollama gets its own
+        sqlx::query("ALTER TABLE chat_configs ADD COLUMN ollama_model TEXT")
+            .execute(&self.pool)
+            .await
+            .ok();
+
+        sqlx::query("ALTER TABLE chat_configs ADD COLUMN temperature REAL DEFAULT 0.7")
+            .execute(&self.pool)
+            .await
+            .ok();
+
+        sqlx::query("ALTER TABLE chat_configs ADD COLUMN context_size INTEGER DEFAULT 10")
+            .execute(&self.pool)
+            .await
+            .ok();
+
+        // Migration: @bot off
+        sqlx::query("ALTER TABLE chat_configs ADD COLUMN paused BOOLEAN DEFAULT FALSE")
+            .execute(&self.pool)
+            .await
+            .ok();
+
+        // Migration: Follow-up window
+        sqlx::query("ALTER TABLE chat_configs ADD COLUMN follow_up_secs INTEGER DEFAULT 0")
+            .execute(&self.pool)
+            .await
+            .ok();
+
+        // Migration: Intent classifier threshold
+        sqlx::query("ALTER TABLE chat_configs ADD COLUMN intent_threshold REAL DEFAULT 0.5")
+            .execute(&self.pool)
+            .await
+            .ok();
+
+        // Migration: Proactive mode
+        sqlx::query("ALTER TABLE chat_configs ADD COLUMN proactive BOOLEAN DEFAULT FALSE")
+            .execute(&self.pool)
+            .await
+            .ok();
+
+        // Migration: Per-chat time zone
+        sqlx::query("ALTER TABLE chat_configs ADD COLUMN timezone TEXT")
+            .execute(&self.pool)
+            .await
+            .ok();
+
         Ok(())
     }
 
     pub async fn get_chat_config(&self, chat_guid: &str) -> Result<Option<ChatConfig>> {
         let row = sqlx::query(
-            "SELECT chat_guid, character_prompt, triggers, trigger_name, use_ollama, created_at, updated_at 
+            "SELECT chat_guid, character_prompt, triggers, trigger_name, use_ollama, reply_mode, voice, model, ollama_model, temperature, context_size, paused, follow_up_secs, intent_threshold, proactive, timezone, created_at, updated_at 
              FROM chat_configs WHERE chat_guid = ?"
         )
         .bind(chat_guid)
@@ -127,6 +384,20 @@ impl Database {
                 triggers,
                 trigger_name: row.get("trigger_name"),
                 use_ollama: row.get("use_ollama"),
+                reply_mode: row
+                    .get::<Option<String>, _>("reply_mode")
+                    .and_then(|mode| ReplyMode::parse(&mode))
+                    .unwrap_or(ReplyMode::Text),
+                voice: row.get("voice"),
+                openai_model: row.get("model"),
+                ollama_model: row.get("ollama_model"),
+                temperature: row.get::<Option<f64>, _>("temperature").unwrap_or(0.7) as f32,
+                context_size: row.get::<Option<i64>, _>("context_size").unwrap_or(10) as usize,
+                paused: row.get::<Option<bool>, _>("paused").unwrap_or(false),
+                follow_up_secs: row.get::<Option<i64>, _>("follow_up_secs").unwrap_or(0) as u64,
+                intent_threshold: row.get::<Option<f64>, _>("intent_threshold").unwrap_or(0.5) as f32,
+                proactive: row.get::<Option<bool>, _>("proactive").unwrap_or(false),
+                timezone: row.get("timezone"),
                 created_at: row.get("created_at"),
                 updated_at: row.get("updated_at"),
             }))
@@ -140,14 +411,25 @@ impl Database {
         
         sqlx::query(r#"
             INSERT OR REPLACE INTO chat_configs 
-            (chat_guid, character_prompt, triggers, trigger_name, use_ollama, created_at, updated_at)
-            VALUES (?, ?, ?, ?, ?, ?, ?)
+            (chat_guid, character_prompt, triggers, trigger_name, use_ollama, reply_mode, voice, model, ollama_model, temperature, context_size, paused, follow_up_secs, intent_threshold, proactive, timezone, created_at, updated_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
         "#)
         .bind(&config.chat_guid)
         .bind(&config.character_prompt)
         .bind(&triggers_json)
         .bind(&config.trigger_name)
         .bind(config.use_ollama)
+        .bind(config.reply_mode.as_str())
+        .bind(&config.voice)
+        .bind(&config.openai_model)
+        .bind(&config.ollama_model)
+        .bind(config.temperature as f64)
+        .bind(config.context_size as i64)
+        .bind(config.paused)
+        .bind(config.follow_up_secs as i64)
+        .bind(config.intent_threshold as f64)
+        .bind(config.proactive)
+        .bind(&config.timezone)
         .bind(config.created_at)
         .bind(Utc::now())
         .execute(&self.pool)
@@ -164,13 +446,16 @@ impl Database {
             MessageRole::System => "system",
         };
 
+        let attachments_json = serde_json::to_string(&message.attachments)?;
+
         sqlx::query(
-            "INSERT INTO chat_contexts (chat_guid, role, content, timestamp) VALUES (?, ?, ?, ?)"
+            "INSERT INTO chat_contexts (chat_guid, role, content, timestamp, attachments) VALUES (?, ?, ?, ?, ?)"
         )
         .bind(chat_guid)
         .bind(role_str)
         .bind(&message.content)
         .bind(message.timestamp)
+        .bind(&attachments_json)
         .execute(&self.pool)
         .await
         .context("Failed to save message")?;
@@ -180,7 +465,7 @@ impl Database {
 
     pub async fn get_recent_messages(&self, chat_guid: &str, limit: i64) -> Result<Vec<Message>> {
         let rows = sqlx::query(
-            "SELECT role, content, timestamp FROM chat_contexts 
+            "SELECT role, content, timestamp, attachments FROM chat_contexts 
              WHERE chat_guid = ? 
              ORDER BY timestamp DESC 
              LIMIT ?"
@@ -191,24 +476,61 @@ impl Database {
         .await
         .context("Failed to fetch recent messages")?;
 
-        let mut messages = Vec::new();
-        for row in rows.into_iter().rev() { // Reverse to get chronological order
-            let role_str: String = row.get("role");
-            let role = match role_str.as_str() {
-                "user" => MessageRole::User,
-                "assistant" => MessageRole::Assistant,
-                "system" => MessageRole::System,
-                _ => MessageRole::User, // Default fallback
-            };
-
-            messages.push(Message {
-                role,
-                content: row.get("content"),
-                timestamp: row.get("timestamp"),
-            });
-        }
+        // Reverse to get chronological order
+        Ok(rows.iter().rev().map(message_from_row).collect())
+    }
+
+    /// The most recent messages in a chat's history after `since`, oldest first.
+    pub async fn get_messages_since(
+        &self,
+        chat_guid: &str,
+        since: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<Message>> {
+        let rows = sqlx::query(
+            "SELECT role, content, timestamp, attachments FROM chat_contexts
+             WHERE chat_guid = ? AND timestamp > ?
+             ORDER BY timestamp DESC
+             LIMIT ?"
+        )
+        .bind(chat_guid)
+        .bind(since)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to fetch messages")?;
 
-        Ok(messages)
+        Ok(rows.iter().rev().map(message_from_row).collect())
+    }
+
+    /// Deletes all stored conversation history for a chat.
+    pub async fn clear_chat_context(&self, chat_guid: &str) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM chat_contexts WHERE chat_guid = ?")
+            .bind(chat_guid)
+            .execute(&self.pool)
+            .await
+            .context("Failed to clear chat context")?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Deletes the most recent `count` stored messages for a chat.
+    pub async fn delete_last_messages(&self, chat_guid: &str, count: i64) -> Result<u64> {
+        let result = sqlx::query(
+            "DELETE FROM chat_contexts WHERE id IN (
+                SELECT id FROM chat_contexts
+                WHERE chat_guid = ?
+                ORDER BY timestamp DESC, id DESC
+                LIMIT ?
+            )"
+        )
+        .bind(chat_guid)
+        .bind(count)
+        .execute(&self.pool)
+        .await
+        .context("Failed to delete recent messages")?;
+
+        Ok(result.rows_affected())
     }
 
     pub async fn is_message_processed(&self, message_guid: &str) -> Result<bool> {
@@ -252,12 +574,24 @@ impl Database {
         Ok(())
     }
 
-    pub async fn queue_message(&self, chat_guid: &str, message_text: &str) -> Result<i64> {
+    pub async fn queue_message(
+        &self,
+        chat_guid: &str,
+        sender: Option<&str>,
+        message_text: &str,
+        attachments: &[BlueBubblesAttachment],
+        mention: bool,
+    ) -> Result<i64> {
+        let attachments_json = serde_json::to_string(attachments)?;
+
         let row = sqlx::query(
-            "INSERT INTO message_queue (chat_guid, message_text) VALUES (?, ?) RETURNING id"
+            "INSERT INTO message_queue (chat_guid, sender, message_text, attachments, mention) VALUES (?, ?, ?, ?, ?) RETURNING id"
         )
         .bind(chat_guid)
+        .bind(sender)
         .bind(message_text)
+        .bind(&attachments_json)
+        .bind(mention)
         .fetch_one(&self.pool)
         .await
         .context("Failed to queue message")?;
@@ -265,9 +599,9 @@ impl Database {
         Ok(row.get("id"))
     }
 
-    pub async fn get_next_queued_message(&self) -> Result<Option<(i64, String, String)>> {
+    pub async fn get_next_queued_message(&self) -> Result<Option<(i64, QueuedMessage)>> {
         let row = sqlx::query(
-            "SELECT id, chat_guid, message_text FROM message_queue 
+            "SELECT id, chat_guid, sender, message_text, attachments, mention FROM message_queue 
              WHERE status = 'pending' 
              ORDER BY queued_at ASC 
              LIMIT 1"
@@ -279,8 +613,11 @@ impl Database {
         if let Some(row) = row {
             let id: i64 = row.get("id");
             let chat_guid: String = row.get("chat_guid");
+            let sender: Option<String> = row.get("sender");
             let message_text: String = row.get("message_text");
-            
+            let attachments = parse_attachments(row.get("attachments"));
+            let mention = row.get::<Option<bool>, _>("mention").unwrap_or(false);
+
             // Mark as processing
             sqlx::query(
                 "UPDATE message_queue SET status = 'processing', processing_started_at = CURRENT_TIMESTAMP WHERE id = ?"
@@ -290,7 +627,8 @@ impl Database {
             .await
             .context("Failed to mark message as processing")?;
 
-            Ok(Some((id, chat_guid, message_text)))
+            let message = QueuedMessage::new(chat_guid, sender, message_text, attachments, mention);
+            Ok(Some((id, message)))
         } else {
             Ok(None)
         }
@@ -314,6 +652,14 @@ impl Database {
         Ok(())
     }
 
+    pub async fn count_queued_messages(&self) -> Result<i64> {
+        let row = sqlx::query("SELECT COUNT(*) AS count FROM message_queue WHERE status = 'pending'")
+            .fetch_one(&self.pool)
+            .await
+            .context("Failed to count queued messages")?;
+        Ok(row.get("count"))
+    }
+
     pub async fn cleanup_old_queue_items(&self, days: i64) -> Result<()> {
         let cutoff = Utc::now() - chrono::Duration::days(days);
         
@@ -325,4 +671,762 @@ impl Database {
 
         Ok(())
     }
-}
\ No newline at end of file
+
+    pub async fn get_cached_attachment(&self, attachment_guid: &str) -> Result<Option<CachedAttachment>> {
+        let row = sqlx::query(
+            "SELECT attachment_guid, chat_guid, content_hash, mime_type, filename, size_bytes, source, description, created_at
+             FROM attachments WHERE attachment_guid = ?"
+        )
+        .bind(attachment_guid)
+        .fetch_optional(&self.pool)
+        .await
+        .context("Failed to fetch cached attachment")?;
+
+        Ok(row.map(|row| cached_attachment_from_row(&row)))
+    }
+
+    pub async fn save_cached_attachment(&self, attachment: &CachedAttachment) -> Result<()> {
+        sqlx::query(r#"
+            INSERT OR REPLACE INTO attachments
+            (attachment_guid, chat_guid, content_hash, mime_type, filename, size_bytes, source, description, created_at, last_accessed_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+        "#)
+        .bind(&attachment.attachment_guid)
+        .bind(&attachment.chat_guid)
+        .bind(&attachment.content_hash)
+        .bind(&attachment.mime_type)
+        .bind(&attachment.filename)
+        .bind(attachment.size_bytes)
+        .bind(&attachment.source)
+        .bind(&attachment.description)
+        .bind(attachment.created_at)
+        .bind(Utc::now())
+        .execute(&self.pool)
+        .await
+        .context("Failed to save cached attachment")?;
+
+        Ok(())
+    }
+
+    /// Images the bot generated in a chat, newest first.
+    pub async fn get_generated_images(&self, chat_guid: &str, limit: i64) -> Result<Vec<CachedAttachment>> {
+        let rows = sqlx::query(
+            "SELECT attachment_guid, chat_guid, content_hash, mime_type, filename, size_bytes, source, description, created_at
+             FROM attachments WHERE chat_guid = ? AND source = 'generated' AND mime_type LIKE 'image/%'
+             ORDER BY created_at DESC LIMIT ?"
+        )
+        .bind(chat_guid)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to fetch generated attachments")?;
+
+        Ok(rows.iter().map(cached_attachment_from_row).collect())
+    }
+
+    pub async fn touch_cached_attachment(&self, attachment_guid: &str) -> Result<()> {
+        sqlx::query("UPDATE attachments SET last_accessed_at = ? WHERE attachment_guid = ?")
+            .bind(Utc::now())
+            .bind(attachment_guid)
+            .execute(&self.pool)
+            .await
+            .context("Failed to update attachment access time")?;
+        Ok(())
+    }
+
+    /// Total size of the cache on disk, counting shared content once.
+    pub async fn cached_attachment_bytes(&self) -> Result<i64> {
+        let row = sqlx::query(
+            "SELECT COALESCE(SUM(size_bytes), 0) AS total FROM
+             (SELECT MAX(size_bytes) AS size_bytes FROM attachments GROUP BY content_hash)"
+        )
+        .fetch_one(&self.pool)
+        .await
+        .context("Failed to compute attachment cache size")?;
+
+        Ok(row.get("total"))
+    }
+
+    pub async fn get_least_recent_attachments(&self, limit: i64) -> Result<Vec<CachedAttachment>> {
+        let rows = sqlx::query(
+            "SELECT attachment_guid, chat_guid, content_hash, mime_type, filename, size_bytes, source, description, created_at
+             FROM attachments ORDER BY last_accessed_at ASC LIMIT ?"
+        )
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to fetch least recently used attachments")?;
+
+        Ok(rows.iter().map(cached_attachment_from_row).collect())
+    }
+
+    /// Deletes a cache entry and returns how many entries still share its content.
+    pub async fn delete_cached_attachment(&self, attachment_guid: &str) -> Result<i64> {
+        let row = sqlx::query("DELETE FROM attachments WHERE attachment_guid = ? RETURNING content_hash")
+            .bind(attachment_guid)
+            .fetch_optional(&self.pool)
+            .await
+            .context("Failed to delete cached attachment")?;
+
+        let Some(row) = row else {
+            return Ok(0);
+        };
+        let content_hash: String = row.get("content_hash");
+
+        let remaining = sqlx::query("SELECT COUNT(*) AS remaining FROM attachments WHERE content_hash = ?")
+            .bind(&content_hash)
+            .fetch_one(&self.pool)
+            .await
+            .context("Failed to count cached attachment references")?;
+
+        Ok(remaining.get("remaining"))
+    }
+
+    /// Saves a persona for a chat, replacing any with the same name.
+    pub async fn save_persona(&self, chat_guid: &str, name: &str, prompt: &str) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO personas (chat_guid, name, prompt, created_at) VALUES (?, ?, ?, ?)
+             ON CONFLICT (chat_guid, name) DO UPDATE SET prompt = excluded.prompt, created_at = excluded.created_at"
+        )
+        .bind(chat_guid)
+        .bind(name)
+        .bind(prompt)
+        .bind(Utc::now())
+        .execute(&self.pool)
+        .await
+        .context("Failed to save persona")?;
+
+        Ok(())
+    }
+
+    /// Looks up a persona by name, preferring the chat's own over shared ones.
+    pub async fn find_persona(&self, chat_guid: &str, name: &str) -> Result<Option<Persona>> {
+        let row = sqlx::query(
+            "SELECT name, chat_guid, prompt, shared FROM personas
+             WHERE name = ? AND (chat_guid = ? OR shared = TRUE)
+             ORDER BY chat_guid = ? DESC, created_at DESC
+             LIMIT 1"
+        )
+        .bind(name)
+        .bind(chat_guid)
+        .bind(chat_guid)
+        .fetch_optional(&self.pool)
+        .await
+        .context("Failed to fetch persona")?;
+
+        Ok(row.as_ref().map(persona_from_row))
+    }
+
+    /// Lists the chat's own personas followed by ones shared from other chats.
+    pub async fn list_personas(&self, chat_guid: &str) -> Result<Vec<Persona>> {
+        let rows = sqlx::query(
+            "SELECT name, chat_guid, prompt, shared FROM personas
+             WHERE chat_guid = ? OR shared = TRUE
+             ORDER BY chat_guid = ? DESC, name"
+        )
+        .bind(chat_guid)
+        .bind(chat_guid)
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to list personas")?;
+
+        Ok(rows.iter().map(persona_from_row).collect())
+    }
+
+    /// Makes one of the chat's personas available to every chat. Returns
+    /// false if the chat has no persona with that name.
+    pub async fn share_persona(&self, chat_guid: &str, name: &str) -> Result<bool> {
+        let result = sqlx::query("UPDATE personas SET shared = TRUE WHERE chat_guid = ? AND name = ?")
+            .bind(chat_guid)
+            .bind(name)
+            .execute(&self.pool)
+            .await
+            .context("Failed to share persona")?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Records a chat's character prompt before it gets replaced.
+    pub async fn push_character_prompt_history(&self, chat_guid: &str, prompt: Option<&str>) -> Result<()> {
+        sqlx::query("INSERT INTO character_prompt_history (chat_guid, prompt, created_at) VALUES (?, ?, ?)")
+            .bind(chat_guid)
+            .bind(prompt)
+            .bind(Utc::now())
+            .execute(&self.pool)
+            .await
+            .context("Failed to save character prompt history")?;
+
+        Ok(())
+    }
+
+    /// Returns the id and prompt of the most recent history entry without
+    /// removing it. `None` means there is no history; a `None` prompt is the
+    /// default character.
+    pub async fn latest_character_prompt_history(&self, chat_guid: &str) -> Result<Option<(i64, Option<String>)>> {
+        let row = sqlx::query(
+            "SELECT id, prompt FROM character_prompt_history
+            WHERE chat_guid = ?
+            ORDER BY id DESC
+            LIMIT 1"
+        )
+        .bind(chat_guid)
+        .fetch_optional(&self.pool)
+        .await
+        .context("Failed to fetch character prompt history")?;
+
+        Ok(row.map(|row| (row.get("id"), row.get("prompt"))))
+    }
+
+    pub async fn delete_character_prompt_history(&self, id: i64) -> Result<()> {
+        sqlx::query("DELETE FROM character_prompt_history WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .context("Failed to delete character prompt history")?;
+
+        Ok(())
+    }
+
+    pub async fn get_chat_owners(&self, chat_guid: &str) -> Result<Vec<String>> {
+        let rows = sqlx::query("SELECT handle FROM chat_roles WHERE chat_guid = ? AND role = 'owner' ORDER BY created_at")
+            .bind(chat_guid)
+            .fetch_all(&self.pool)
+            .await
+            .context("Failed to fetch chat owners")?;
+
+        Ok(rows.iter().map(|row| row.get("handle")).collect())
+    }
+
+    /// Returns false if the handle was already an owner.
+    pub async fn add_chat_owner(&self, chat_guid: &str, handle: &str) -> Result<bool> {
+        let result = sqlx::query("INSERT OR IGNORE INTO chat_roles (chat_guid, handle, role) VALUES (?, ?, 'owner')")
+            .bind(chat_guid)
+            .bind(handle)
+            .execute(&self.pool)
+            .await
+            .context("Failed to add chat owner")?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Returns false if the handle wasn't an owner.
+    pub async fn remove_chat_owner(&self, chat_guid: &str, handle: &str) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM chat_roles WHERE chat_guid = ? AND handle = ?")
+            .bind(chat_guid)
+            .bind(handle)
+            .execute(&self.pool)
+            .await
+            .context("Failed to remove chat owner")?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn schedule_message(&self, chat_guid: &str, message: &str, send_at: DateTime<Utc>) -> Result<i64> {
+        let row = sqlx::query(
+            "INSERT INTO scheduled_messages (chat_guid, message, send_at) VALUES (?, ?, ?) RETURNING id"
+        )
+        .bind(chat_guid)
+        .bind(message)
+        .bind(send_at)
+        .fetch_one(&self.pool)
+        .await
+        .context("Failed to schedule message")?;
+
+        Ok(row.get("id"))
+    }
+
+    pub async fn get_due_scheduled_messages(&self, now: DateTime<Utc>) -> Result<Vec<ScheduledMessage>> {
+        let rows = sqlx::query(
+            "SELECT id, chat_guid, message, send_at, attempts FROM scheduled_messages
+             WHERE status = 'pending' AND COALESCE(retry_at, send_at) <= ?
+             ORDER BY send_at"
+        )
+        .bind(now)
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to fetch due scheduled messages")?;
+
+        Ok(rows.iter().map(scheduled_message_from_row).collect())
+    }
+
+    pub async fn get_pending_scheduled_messages(&self, chat_guid: &str) -> Result<Vec<ScheduledMessage>> {
+        let rows = sqlx::query(
+            "SELECT id, chat_guid, message, send_at, attempts FROM scheduled_messages
+             WHERE chat_guid = ? AND status = 'pending'
+             ORDER BY send_at"
+        )
+        .bind(chat_guid)
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to fetch scheduled messages")?;
+
+        Ok(rows.iter().map(scheduled_message_from_row).collect())
+    }
+
+    /// Sets a scheduled message's status: 'sent', 'cancelled' or 'failed'.
+    pub async fn set_scheduled_message_status(&self, id: i64, status: &str) -> Result<()> {
+        sqlx::query("UPDATE scheduled_messages SET status = ? WHERE id = ?")
+            .bind(status)
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .context("Failed to update scheduled message")?;
+
+        Ok(())
+    }
+
+    /// Leaves a message that failed to send pending, to try again at `retry_at`.
+    pub async fn retry_scheduled_message(&self, id: i64, retry_at: DateTime<Utc>) -> Result<()> {
+        sqlx::query("UPDATE scheduled_messages SET attempts = attempts + 1, retry_at = ? WHERE id = ?")
+            .bind(retry_at)
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .context("Failed to reschedule scheduled message")?;
+
+        Ok(())
+    }
+
+    /// Cancels a pending message in the chat. Returns false if there was none.
+    pub async fn cancel_scheduled_message(&self, chat_guid: &str, id: i64) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE scheduled_messages SET status = 'cancelled' WHERE id = ? AND chat_guid = ? AND status = 'pending'"
+        )
+        .bind(id)
+        .bind(chat_guid)
+        .execute(&self.pool)
+        .await
+        .context("Failed to cancel scheduled message")?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn add_recurring_job(
+        &self,
+        chat_guid: &str,
+        schedule: &str,
+        description: &str,
+        prompt: &str,
+        next_run_at: DateTime<Utc>,
+    ) -> Result<i64> {
+        let row = sqlx::query(
+            "INSERT INTO recurring_jobs (chat_guid, schedule, description, prompt, next_run_at)
+             VALUES (?, ?, ?, ?, ?) RETURNING id"
+        )
+        .bind(chat_guid)
+        .bind(schedule)
+        .bind(description)
+        .bind(prompt)
+        .bind(next_run_at)
+        .fetch_one(&self.pool)
+        .await
+        .context("Failed to add recurring job")?;
+
+        Ok(row.get("id"))
+    }
+
+    pub async fn get_recurring_jobs(&self, chat_guid: &str) -> Result<Vec<RecurringJob>> {
+        let rows = sqlx::query(
+            "SELECT id, chat_guid, schedule, description, prompt, next_run_at, last_run_at FROM recurring_jobs
+             WHERE chat_guid = ?
+             ORDER BY id"
+        )
+        .bind(chat_guid)
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to fetch recurring jobs")?;
+
+        Ok(rows.iter().map(recurring_job_from_row).collect())
+    }
+
+    pub async fn get_due_recurring_jobs(&self, now: DateTime<Utc>) -> Result<Vec<RecurringJob>> {
+        let rows = sqlx::query(
+            "SELECT id, chat_guid, schedule, description, prompt, next_run_at, last_run_at FROM recurring_jobs
+             WHERE next_run_at <= ?
+             ORDER BY next_run_at"
+        )
+        .bind(now)
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to fetch due recurring jobs")?;
+
+        Ok(rows.iter().map(recurring_job_from_row).collect())
+    }
+
+    /// Records that a job ran and when it should run next.
+    pub async fn reschedule_recurring_job(&self, id: i64, next_run_at: DateTime<Utc>) -> Result<()> {
+        sqlx::query("UPDATE recurring_jobs SET next_run_at = ?, last_run_at = ? WHERE id = ?")
+            .bind(next_run_at)
+            .bind(Utc::now())
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .context("Failed to reschedule recurring job")?;
+
+        Ok(())
+    }
+
+    /// Deletes a job in the chat. Returns false if there was none.
+    pub async fn delete_recurring_job(&self, chat_guid: &str, id: i64) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM recurring_jobs WHERE id = ? AND chat_guid = ?")
+            .bind(id)
+            .bind(chat_guid)
+            .execute(&self.pool)
+            .await
+            .context("Failed to delete recurring job")?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn create_poll(
+        &self,
+        chat_guid: &str,
+        question: &str,
+        options: &[String],
+        closes_at: DateTime<Utc>,
+    ) -> Result<Poll> {
+        let row = sqlx::query(
+            "INSERT INTO polls (chat_guid, question, options, closes_at) VALUES (?, ?, ?, ?) RETURNING id, created_at"
+        )
+        .bind(chat_guid)
+        .bind(question)
+        .bind(serde_json::to_string(options)?)
+        .bind(closes_at)
+        .fetch_one(&self.pool)
+        .await
+        .context("Failed to create poll")?;
+
+        Ok(Poll {
+            id: row.get("id"),
+            chat_guid: chat_guid.to_string(),
+            question: question.to_string(),
+            options: options.to_vec(),
+            message_guid: None,
+            created_at: row.get("created_at"),
+        })
+    }
+
+    /// The chat's newest open poll, which plain "2" or "sushi" replies vote in.
+    pub async fn get_open_poll(&self, chat_guid: &str) -> Result<Option<Poll>> {
+        let row = sqlx::query(
+            "SELECT id, chat_guid, question, options, message_guid, created_at FROM polls
+             WHERE chat_guid = ? AND status = 'open'
+             ORDER BY id DESC LIMIT 1"
+        )
+        .bind(chat_guid)
+        .fetch_optional(&self.pool)
+        .await
+        .context("Failed to fetch open poll")?;
+
+        Ok(row.as_ref().map(poll_from_row))
+    }
+
+    pub async fn get_open_poll_by_id(&self, chat_guid: &str, id: i64) -> Result<Option<Poll>> {
+        let row = sqlx::query(
+            "SELECT id, chat_guid, question, options, message_guid, created_at FROM polls
+             WHERE chat_guid = ? AND id = ? AND status = 'open'"
+        )
+        .bind(chat_guid)
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await
+        .context("Failed to fetch poll")?;
+
+        Ok(row.as_ref().map(poll_from_row))
+    }
+
+    /// The open poll posted as the given message, for tapback votes.
+    pub async fn get_open_poll_by_message(&self, message_guid: &str) -> Result<Option<Poll>> {
+        let row = sqlx::query(
+            "SELECT id, chat_guid, question, options, message_guid, created_at FROM polls
+             WHERE message_guid = ? AND status = 'open'"
+        )
+        .bind(message_guid)
+        .fetch_optional(&self.pool)
+        .await
+        .context("Failed to fetch poll by message")?;
+
+        Ok(row.as_ref().map(poll_from_row))
+    }
+
+    pub async fn get_expired_polls(&self, now: DateTime<Utc>) -> Result<Vec<Poll>> {
+        let rows = sqlx::query(
+            "SELECT id, chat_guid, question, options, message_guid, created_at FROM polls
+             WHERE status = 'open' AND closes_at <= ?"
+        )
+        .bind(now)
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to fetch expired polls")?;
+
+        Ok(rows.iter().map(poll_from_row).collect())
+    }
+
+    pub async fn set_poll_message_guid(&self, poll_id: i64, message_guid: &str) -> Result<()> {
+        sqlx::query("UPDATE polls SET message_guid = ? WHERE id = ? AND message_guid IS NULL")
+            .bind(message_guid)
+            .bind(poll_id)
+            .execute(&self.pool)
+            .await
+            .context("Failed to set poll message")?;
+
+        Ok(())
+    }
+
+    /// Records a vote. Each voter has one vote per poll; voting again changes it.
+    pub async fn record_poll_vote(&self, poll_id: i64, voter: &str, option_index: usize) -> Result<()> {
+        sqlx::query(
+            "INSERT OR REPLACE INTO poll_votes (poll_id, voter, option_index, voted_at) VALUES (?, ?, ?, ?)"
+        )
+        .bind(poll_id)
+        .bind(voter)
+        .bind(option_index as i64)
+        .bind(Utc::now())
+        .execute(&self.pool)
+        .await
+        .context("Failed to record poll vote")?;
+
+        Ok(())
+    }
+
+    /// Takes back a vote, if it's still for that option.
+    pub async fn remove_poll_vote(&self, poll_id: i64, voter: &str, option_index: usize) -> Result<()> {
+        sqlx::query("DELETE FROM poll_votes WHERE poll_id = ? AND voter = ? AND option_index = ?")
+            .bind(poll_id)
+            .bind(voter)
+            .bind(option_index as i64)
+            .execute(&self.pool)
+            .await
+            .context("Failed to remove poll vote")?;
+
+        Ok(())
+    }
+
+    /// Vote counts for each of the poll's options.
+    pub async fn get_poll_votes(&self, poll: &Poll) -> Result<Vec<i64>> {
+        let rows = sqlx::query(
+            "SELECT option_index, COUNT(*) AS votes FROM poll_votes WHERE poll_id = ? GROUP BY option_index"
+        )
+        .bind(poll.id)
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to count poll votes")?;
+
+        let mut votes = vec![0; poll.options.len()];
+        for row in rows {
+            let index: i64 = row.get("option_index");
+            if let Some(count) = votes.get_mut(index as usize) {
+                *count = row.get("votes");
+            }
+        }
+        Ok(votes)
+    }
+
+    /// Closes an open poll. Returns false if it was already closed.
+    pub async fn close_poll(&self, poll_id: i64) -> Result<bool> {
+        let result = sqlx::query("UPDATE polls SET status = 'closed' WHERE id = ? AND status = 'open'")
+            .bind(poll_id)
+            .execute(&self.pool)
+            .await
+            .context("Failed to close poll")?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn record_usage(&self, event: &UsageEvent) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO usage_events (chat_guid, sender, provider, kind, model, prompt_tokens,
+                                       completion_tokens, image_count, latency_ms, cost_usd, created_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
+        )
+        .bind(&event.chat_guid)
+        .bind(&event.sender)
+        .bind(&event.provider)
+        .bind(&event.kind)
+        .bind(&event.model)
+        .bind(event.prompt_tokens)
+        .bind(event.completion_tokens)
+        .bind(event.image_count)
+        .bind(event.latency_ms)
+        .bind(event.cost_usd)
+        .bind(Utc::now())
+        .execute(&self.pool)
+        .await
+        .context("Failed to record usage")?;
+
+        Ok(())
+    }
+
+    /// Usage in a chat since a time, per provider and model, most expensive first.
+    pub async fn get_usage_summary(&self, chat_guid: &str, since: DateTime<Utc>) -> Result<Vec<UsageSummary>> {
+        let rows = sqlx::query(
+            "SELECT COALESCE(provider, 'openai') AS provider, model,
+                    COUNT(*) AS requests,
+                    COALESCE(SUM(prompt_tokens + completion_tokens), 0) AS tokens,
+                    COALESCE(SUM(image_count), 0) AS images,
+                    COALESCE(SUM(cost_usd), 0.0) AS cost,
+                    CAST(COALESCE(AVG(latency_ms), 0) AS INTEGER) AS avg_latency_ms
+             FROM usage_events
+             WHERE chat_guid = ? AND created_at >= ?
+             GROUP BY 1, model
+             ORDER BY cost DESC, tokens DESC"
+        )
+        .bind(chat_guid)
+        .bind(since)
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to summarize usage")?;
+
+        Ok(rows
+            .iter()
+            .map(|row| UsageSummary {
+                provider: row.get("provider"),
+                model: row.get("model"),
+                requests: row.get("requests"),
+                tokens: row.get("tokens"),
+                images: row.get("images"),
+                cost_usd: row.get("cost"),
+                avg_latency_ms: row.get("avg_latency_ms"),
+            })
+            .collect())
+    }
+
+    /// The heaviest users in a chat since a time.
+    pub async fn get_usage_by_sender(
+        &self,
+        chat_guid: &str,
+        since: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<SenderUsage>> {
+        let rows = sqlx::query(
+            "SELECT sender,
+                    COALESCE(SUM(prompt_tokens + completion_tokens), 0) AS tokens,
+                    COALESCE(SUM(cost_usd), 0.0) AS cost
+             FROM usage_events
+             WHERE chat_guid = ? AND created_at >= ?
+             GROUP BY sender
+             ORDER BY cost DESC, tokens DESC
+             LIMIT ?"
+        )
+        .bind(chat_guid)
+        .bind(since)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to summarize usage by sender")?;
+
+        Ok(rows
+            .iter()
+            .map(|row| SenderUsage {
+                sender: row.get("sender"),
+                tokens: row.get("tokens"),
+                cost_usd: row.get("cost"),
+            })
+            .collect())
+    }
+
+    /// Tokens used and dollars spent since a time, in one chat or by one
+    /// sender across all chats (or both, or everything).
+    pub async fn get_usage_since(
+        &self,
+        chat_guid: Option<&str>,
+        sender: Option<&str>,
+        since: DateTime<Utc>,
+    ) -> Result<(i64, f64)> {
+        let row = sqlx::query(
+            "SELECT COALESCE(SUM(prompt_tokens + completion_tokens), 0) AS tokens,
+                    COALESCE(SUM(cost_usd), 0.0) AS cost
+             FROM usage_events
+             WHERE created_at >= ? AND (? IS NULL OR chat_guid = ?) AND (? IS NULL OR sender = ?)"
+        )
+        .bind(since)
+        .bind(chat_guid)
+        .bind(chat_guid)
+        .bind(sender)
+        .bind(sender)
+        .fetch_one(&self.pool)
+        .await
+        .context("Failed to sum usage")?;
+
+        Ok((row.get("tokens"), row.get("cost")))
+    }
+}
+
+fn poll_from_row(row: &sqlx::sqlite::SqliteRow) -> Poll {
+    let options: String = row.get("options");
+    Poll {
+        id: row.get("id"),
+        chat_guid: row.get("chat_guid"),
+        question: row.get("question"),
+        options: serde_json::from_str(&options).unwrap_or_default(),
+        message_guid: row.get("message_guid"),
+        created_at: row.get("created_at"),
+    }
+}
+
+fn recurring_job_from_row(row: &sqlx::sqlite::SqliteRow) -> RecurringJob {
+    RecurringJob {
+        id: row.get("id"),
+        chat_guid: row.get("chat_guid"),
+        schedule: row.get("schedule"),
+        description: row.get("description"),
+        prompt: row.get("prompt"),
+        next_run_at: row.get("next_run_at"),
+        last_run_at: row.get("last_run_at"),
+    }
+}
+
+fn scheduled_message_from_row(row: &sqlx::sqlite::SqliteRow) -> ScheduledMessage {
+    ScheduledMessage {
+        id: row.get("id"),
+        chat_guid: row.get("chat_guid"),
+        message: row.get("message"),
+        send_at: row.get("send_at"),
+        attempts: row.get::<Option<i64>, _>("attempts").unwrap_or(0),
+    }
+}
+
+fn message_from_row(row: &sqlx::sqlite::SqliteRow) -> Message {
+    let role_str: String = row.get("role");
+    let role = match role_str.as_str() {
+        "user" => MessageRole::User,
+        "assistant" => MessageRole::Assistant,
+        "system" => MessageRole::System,
+        _ => MessageRole::User, // Default fallback
+    };
+
+    Message {
+        role,
+        content: row.get("content"),
+        timestamp: row.get("timestamp"),
+        attachments: parse_attachments(row.get("attachments")),
+    }
+}
+
+fn persona_from_row(row: &sqlx::sqlite::SqliteRow) -> Persona {
+    Persona {
+        name: row.get("name"),
+        chat_guid: row.get("chat_guid"),
+        prompt: row.get("prompt"),
+        shared: row.get("shared"),
+    }
+}
+
+fn parse_attachments(json: Option<String>) -> Vec<BlueBubblesAttachment> {
+    json.and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+fn cached_attachment_from_row(row: &sqlx::sqlite::SqliteRow) -> CachedAttachment {
+    CachedAttachment {
+        attachment_guid: row.get("attachment_guid"),
+        chat_guid: row.get("chat_guid"),
+        content_hash: row.get("content_hash"),
+        mime_type: row.get("mime_type"),
+        filename: row.get("filename"),
+        size_bytes: row.get("size_bytes"),
+        source: row.get("source"),
+        description: row.get("description"),
+        created_at: row.get("created_at"),
+    }
+}
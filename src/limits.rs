@@ -0,0 +1,308 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+/// How often someone is told they've been throttled, so a spammer doesn't
+/// get a throttling reply for every message.
+const NOTICE_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// Default prices (USD per million input/output tokens) for the models the
+/// bot uses out of the box. Anything else is free unless `MODEL_PRICES` says
+/// otherwise, which suits local Ollama models.
+const DEFAULT_MODEL_PRICES: &[(&str, f64, f64)] = &[
+    ("gpt-4o-mini", 0.15, 0.60),
+    ("gpt-4o", 2.50, 10.00),
+];
+
+/// Price per million tokens for one model.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ModelPrice {
+    pub input: f64,
+    pub output: f64,
+}
+
+/// What each model costs, from the defaults plus `MODEL_PRICES`
+/// ("gpt-4o=2.5/10,llama3=0/0").
+#[derive(Debug, Clone)]
+pub struct ModelPrices {
+    prices: Vec<(String, ModelPrice)>,
+}
+
+impl ModelPrices {
+    pub fn parse(spec: &str) -> Self {
+        let mut prices: Vec<(String, ModelPrice)> = DEFAULT_MODEL_PRICES
+            .iter()
+            .map(|(model, input, output)| (model.to_string(), ModelPrice { input: *input, output: *output }))
+            .collect();
+
+        for entry in spec.split(',').map(str::trim).filter(|entry| !entry.is_empty()) {
+            let parsed = entry.split_once('=').and_then(|(model, price)| {
+                let (input, output) = price.split_once('/')?;
+                let price = ModelPrice {
+                    input: input.trim().parse().ok()?,
+                    output: output.trim().parse().ok()?,
+                };
+                Some((model.trim().to_lowercase(), price))
+            });
+
+            match parsed {
+                Some((model, price)) => {
+                    prices.retain(|(known, _)| *known != model);
+                    prices.push((model, price));
+                }
+                None => warn!("Ignoring bad MODEL_PRICES entry '{}'", entry),
+            }
+        }
+
+        Self { prices }
+    }
+
+    /// The price for a model, matching dated variants ("gpt-4o-2024-08-06")
+    /// by their longest known prefix.
+    pub fn get(&self, model: &str) -> Option<ModelPrice> {
+        let model = model.to_lowercase();
+        self.prices
+            .iter()
+            .filter(|(known, _)| model.starts_with(known.as_str()))
+            .max_by_key(|(known, _)| known.len())
+            .map(|(_, price)| *price)
+    }
+
+    /// Cost in USD of one completion.
+    pub fn cost(&self, model: &str, prompt_tokens: i64, completion_tokens: i64) -> f64 {
+        self.get(model).map_or(0.0, |price| {
+            (prompt_tokens as f64 * price.input + completion_tokens as f64 * price.output) / 1_000_000.0
+        })
+    }
+}
+
+/// A classic token bucket: holds up to `capacity` messages and refills at a
+/// steady rate, so short bursts are fine but sustained spam isn't.
+#[derive(Debug, Clone)]
+pub struct TokenBucket {
+    capacity: f64,
+    tokens: f64,
+    refill_per_sec: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    /// A bucket allowing `per_minute` messages a minute, all at once if need be.
+    pub fn per_minute(per_minute: u32) -> Self {
+        Self {
+            capacity: per_minute as f64,
+            tokens: per_minute as f64,
+            refill_per_sec: per_minute as f64 / 60.0,
+            last_refill: Instant::now(),
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.refill_per_sec).min(self.capacity);
+        self.last_refill = now;
+    }
+
+    fn has_token(&mut self, now: Instant) -> bool {
+        self.refill(now);
+        self.tokens >= 1.0
+    }
+
+    fn take(&mut self) {
+        self.tokens -= 1.0;
+    }
+}
+
+/// Which limit stopped a message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Throttle {
+    Sender,
+    Chat,
+    SenderQuota,
+    ChatQuota,
+}
+
+impl Throttle {
+    pub fn reply(&self) -> &'static str {
+        match self {
+            Throttle::Sender => "⏳ You're sending me messages faster than I can keep up. Give me a minute and try again.",
+            Throttle::Chat => "⏳ This chat is keeping me busy! Give me a minute and try again.",
+            Throttle::SenderQuota => "💸 You've used up your AI budget for today. Try again tomorrow!",
+            Throttle::ChatQuota => "💸 This chat has used up today's AI budget. I'll be back tomorrow!",
+        }
+    }
+}
+
+/// Per-sender and per-chat message rate limits. A limit of 0 turns it off.
+pub struct RateLimiter {
+    sender_per_minute: u32,
+    chat_per_minute: u32,
+    senders: HashMap<String, TokenBucket>,
+    chats: HashMap<String, TokenBucket>,
+    notices: HashMap<String, Instant>,
+}
+
+impl RateLimiter {
+    pub fn new(sender_per_minute: u32, chat_per_minute: u32) -> Self {
+        Self {
+            sender_per_minute,
+            chat_per_minute,
+            senders: HashMap::new(),
+            chats: HashMap::new(),
+            notices: HashMap::new(),
+        }
+    }
+
+    /// Lets a message through if both the sender and the chat have room,
+    /// using up one token from each. A throttled message uses up nothing.
+    pub fn check(&mut self, chat_guid: &str, sender: Option<&str>) -> Result<(), Throttle> {
+        self.check_at(chat_guid, sender, Instant::now())
+    }
+
+    fn check_at(&mut self, chat_guid: &str, sender: Option<&str>, now: Instant) -> Result<(), Throttle> {
+        let sender_key = sender
+            .filter(|_| self.sender_per_minute > 0)
+            .map(|sender| format!("{}:{}", chat_guid, sender));
+
+        if let Some(key) = &sender_key {
+            let per_minute = self.sender_per_minute;
+            let bucket = self
+                .senders
+                .entry(key.clone())
+                .or_insert_with(|| TokenBucket::per_minute(per_minute));
+            if !bucket.has_token(now) {
+                return Err(Throttle::Sender);
+            }
+        }
+
+        if self.chat_per_minute > 0 {
+            let per_minute = self.chat_per_minute;
+            let bucket = self
+                .chats
+                .entry(chat_guid.to_string())
+                .or_insert_with(|| TokenBucket::per_minute(per_minute));
+            if !bucket.has_token(now) {
+                return Err(Throttle::Chat);
+            }
+            bucket.take();
+        }
+
+        if let Some(bucket) = sender_key.and_then(|key| self.senders.get_mut(&key)) {
+            bucket.take();
+        }
+        Ok(())
+    }
+
+    /// Whether to tell someone they've been throttled, at most once every
+    /// few minutes per key.
+    pub fn should_notify(&mut self, throttle: Throttle, chat_guid: &str, sender: Option<&str>) -> bool {
+        let key = format!("{:?}:{}:{}", throttle, chat_guid, sender.unwrap_or_default());
+        let now = Instant::now();
+        match self.notices.get(&key) {
+            Some(last) if now.duration_since(*last) < NOTICE_INTERVAL => false,
+            _ => {
+                self.notices.insert(key, now);
+                true
+            }
+        }
+    }
+
+    /// Drops buckets that have refilled completely; they'd be recreated full.
+    pub fn cleanup(&mut self) {
+        let now = Instant::now();
+        for buckets in [&mut self.senders, &mut self.chats] {
+            buckets.retain(|_, bucket| {
+                bucket.refill(now);
+                bucket.tokens < bucket.capacity
+            });
+        }
+        self.notices
+            .retain(|_, last| now.duration_since(*last) < NOTICE_INTERVAL);
+    }
+}
+
+/// Daily token and spend caps. Zero means unlimited.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Quota {
+    pub tokens: i64,
+    pub spend: f64,
+}
+
+impl Quota {
+    pub fn from_env(tokens_var: &str, spend_var: &str) -> Self {
+        Self {
+            tokens: env_number(tokens_var),
+            spend: env_number(spend_var),
+        }
+    }
+
+    /// Whether usage so far has reached either cap.
+    pub fn exceeded(&self, tokens: i64, spend: f64) -> bool {
+        (self.tokens > 0 && tokens >= self.tokens) || (self.spend > 0.0 && spend >= self.spend)
+    }
+}
+
+fn env_number<T: std::str::FromStr + Default>(name: &str) -> T {
+    std::env::var(name)
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_model_prices() {
+        let prices = ModelPrices::parse("gpt-4o=5/15, llama3=0.1/0.2, nonsense");
+
+        assert_eq!(prices.get("gpt-4o"), Some(ModelPrice { input: 5.0, output: 15.0 }));
+        assert_eq!(prices.get("gpt-4o-mini"), Some(ModelPrice { input: 0.15, output: 0.60 }));
+        assert_eq!(prices.get("GPT-4o-2024-08-06"), Some(ModelPrice { input: 5.0, output: 15.0 }));
+        assert_eq!(prices.get("mistral"), None);
+
+        let cost = prices.cost("gpt-4o-mini", 1_000_000, 500_000);
+        assert!((cost - 0.45).abs() < 1e-9);
+        assert_eq!(prices.cost("mistral", 1000, 1000), 0.0);
+    }
+
+    #[test]
+    fn test_token_bucket_refills() {
+        let mut limiter = RateLimiter::new(2, 0);
+        let start = Instant::now();
+
+        assert!(limiter.check_at("chat", Some("alice"), start).is_ok());
+        assert!(limiter.check_at("chat", Some("alice"), start).is_ok());
+        assert_eq!(limiter.check_at("chat", Some("alice"), start), Err(Throttle::Sender));
+        // Someone else in the same chat has their own bucket
+        assert!(limiter.check_at("chat", Some("bob"), start).is_ok());
+
+        // Two a minute: one token back after 30 seconds
+        let later = start + Duration::from_secs(30);
+        assert!(limiter.check_at("chat", Some("alice"), later).is_ok());
+        assert!(limiter.check_at("chat", Some("alice"), later).is_err());
+    }
+
+    #[test]
+    fn test_chat_limit_spares_sender_tokens() {
+        let mut limiter = RateLimiter::new(5, 1);
+        let now = Instant::now();
+
+        assert!(limiter.check_at("chat", Some("alice"), now).is_ok());
+        assert_eq!(limiter.check_at("chat", Some("bob"), now), Err(Throttle::Chat));
+        assert!(limiter.check_at("other", Some("bob"), now).is_ok());
+    }
+
+    #[test]
+    fn test_quota() {
+        let quota = Quota { tokens: 1000, spend: 0.0 };
+        assert!(!quota.exceeded(999, 50.0));
+        assert!(quota.exceeded(1000, 0.0));
+
+        let quota = Quota { tokens: 0, spend: 1.0 };
+        assert!(quota.exceeded(0, 1.25));
+        assert!(!Quota::default().exceeded(i64::MAX, f64::MAX));
+    }
+}
@@ -6,12 +6,19 @@ mod chat_agent;
 mod orchestrator;
 mod types;
 mod commands;
+mod media;
+mod attachment_cache;
+mod limits;
+mod metrics;
+mod polls;
+mod scheduling;
+mod triggers;
+mod usage;
 
 use anyhow::Result;
 use config::Config;
 use orchestrator::BotOrchestrator;
 use tracing::{info, error};
-use tracing_subscriber;
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -0,0 +1,627 @@
+use anyhow::{Context, Result};
+use base64::Engine;
+use image::{imageops::FilterType, DynamicImage, GenericImageView, ImageFormat as CodecFormat};
+use std::io::Cursor;
+use std::path::Path;
+use tokio::process::Command;
+use tracing::{debug, info};
+
+use crate::config::Config;
+use crate::types::BlueBubblesAttachment;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    Jpeg,
+    Png,
+    Gif,
+    Webp,
+    Heic,
+}
+
+impl ImageFormat {
+    /// Detects the format from the file's magic bytes, falling back to the
+    /// declared MIME type when the bytes aren't recognised.
+    pub fn detect(data: &[u8], mime_type: Option<&str>) -> Option<Self> {
+        Self::from_magic(data).or_else(|| mime_type.and_then(Self::from_mime_type))
+    }
+
+    fn from_magic(data: &[u8]) -> Option<Self> {
+        if data.starts_with(&[0xFF, 0xD8, 0xFF]) {
+            Some(Self::Jpeg)
+        } else if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+            Some(Self::Png)
+        } else if data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a") {
+            Some(Self::Gif)
+        } else if data.len() >= 12 && &data[0..4] == b"RIFF" && &data[8..12] == b"WEBP" {
+            Some(Self::Webp)
+        } else if data.len() >= 12 && &data[4..8] == b"ftyp" {
+            // ISO base media file: HEIC/HEIF brands. The generic mif1/msf1
+            // brands are shared with AVIF, which lists avif/avis as compatible.
+            match &data[8..12] {
+                b"heic" | b"heix" | b"hevc" | b"hevx" | b"heim" | b"heis" => Some(Self::Heic),
+                b"mif1" | b"msf1" if !is_avif(data) => Some(Self::Heic),
+                _ => None,
+            }
+        } else {
+            None
+        }
+    }
+
+    pub fn from_mime_type(mime_type: &str) -> Option<Self> {
+        match mime_type.to_lowercase().as_str() {
+            "image/jpeg" | "image/jpg" => Some(Self::Jpeg),
+            "image/png" => Some(Self::Png),
+            "image/gif" => Some(Self::Gif),
+            "image/webp" => Some(Self::Webp),
+            "image/heic" | "image/heif" | "image/heic-sequence" | "image/heif-sequence" => {
+                Some(Self::Heic)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            Self::Jpeg => "image/jpeg",
+            Self::Png => "image/png",
+            Self::Gif => "image/gif",
+            Self::Webp => "image/webp",
+            Self::Heic => "image/heic",
+        }
+    }
+
+    /// Whether vision models accept this format as-is.
+    pub fn is_vision_supported(&self) -> bool {
+        !matches!(self, Self::Heic)
+    }
+
+    /// Formats the image edit endpoint accepts.
+    pub fn is_edit_supported(&self) -> bool {
+        matches!(self, Self::Jpeg | Self::Png | Self::Webp)
+    }
+
+    fn codec(&self) -> Option<CodecFormat> {
+        match self {
+            Self::Jpeg => Some(CodecFormat::Jpeg),
+            Self::Png => Some(CodecFormat::Png),
+            Self::Gif => Some(CodecFormat::Gif),
+            Self::Webp => Some(CodecFormat::WebP),
+            Self::Heic => None,
+        }
+    }
+}
+
+/// Whether an ISO base media file lists an AVIF brand among its compatible
+/// brands, which follow the major brand and minor version in the `ftyp` box.
+fn is_avif(data: &[u8]) -> bool {
+    let box_len = u32::from_be_bytes([data[0], data[1], data[2], data[3]]) as usize;
+    data.get(16..box_len.min(data.len()))
+        .unwrap_or_default()
+        .chunks_exact(4)
+        .any(|brand| brand == b"avif" || brand == b"avis")
+}
+
+/// An image ready to be sent to a vision model.
+#[derive(Debug, Clone)]
+pub struct VisionImage {
+    pub data: Vec<u8>,
+    pub mime_type: String,
+}
+
+impl VisionImage {
+    pub fn data_url(&self) -> String {
+        format!(
+            "data:{};base64,{}",
+            self.mime_type,
+            base64::engine::general_purpose::STANDARD.encode(&self.data)
+        )
+    }
+}
+
+/// Longest filename the bot gives a file it sends.
+const MAX_FILENAME_CHARS: usize = 100;
+
+/// A file to send to a chat as an attachment.
+#[derive(Debug, Clone)]
+pub struct OutgoingFile {
+    pub data: Vec<u8>,
+    pub filename: String,
+    pub mime_type: String,
+}
+
+impl OutgoingFile {
+    /// Builds a file, sniffing its MIME type from the contents and name.
+    pub fn new(data: Vec<u8>, filename: impl Into<String>) -> Self {
+        let filename = filename.into();
+        let mime_type = sniff_mime_type(&data, &filename).to_string();
+        Self {
+            data,
+            filename,
+            mime_type,
+        }
+    }
+
+    /// Builds a file named `<stem>.<ext>`, picking the extension from the
+    /// sniffed contents.
+    pub fn with_stem(data: Vec<u8>, stem: &str) -> Self {
+        let mime_type = sniff_mime_type(&data, "");
+        let filename = format!("{}.{}", stem, extension_for_mime_type(mime_type));
+        Self {
+            data,
+            filename,
+            mime_type: mime_type.to_string(),
+        }
+    }
+}
+
+pub fn extension_for_mime_type(mime_type: &str) -> &'static str {
+    match mime_type {
+        "image/jpeg" => "jpg",
+        "image/png" => "png",
+        "image/gif" => "gif",
+        "image/webp" => "webp",
+        "image/heic" => "heic",
+        "application/pdf" => "pdf",
+        "audio/mpeg" => "mp3",
+        "audio/mp4" => "m4a",
+        "audio/x-caf" => "caf",
+        "audio/wav" => "wav",
+        "audio/ogg" => "ogg",
+        "text/vcard" => "vcf",
+        "text/calendar" => "ics",
+        "text/plain" => "txt",
+        _ => "bin",
+    }
+}
+
+/// Cleans up a filename that came from the model: keeps only the last path
+/// component, drops control characters and leading dots, and caps the length
+/// while keeping the extension. Returns None if nothing usable is left.
+pub fn safe_filename(name: &str) -> Option<String> {
+    let name = name.rsplit(['/', '\\']).next().unwrap_or_default();
+    let name: String = name.chars().filter(|c| !c.is_control()).collect();
+    let name = name.trim().trim_start_matches('.').trim();
+    if name.is_empty() {
+        return None;
+    }
+    if name.chars().count() <= MAX_FILENAME_CHARS {
+        return Some(name.to_string());
+    }
+
+    let (stem, extension) = match name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() && extension.chars().count() <= 10 => {
+            (stem, format!(".{}", extension))
+        }
+        _ => (name, String::new()),
+    };
+    let stem: String = stem
+        .chars()
+        .take(MAX_FILENAME_CHARS - extension.chars().count())
+        .collect();
+    Some(format!("{}{}", stem.trim_end(), extension))
+}
+
+/// Guesses a MIME type from magic bytes, falling back to the file extension.
+pub fn sniff_mime_type(data: &[u8], filename: &str) -> &'static str {
+    if let Some(format) = ImageFormat::from_magic(data) {
+        return format.mime_type();
+    }
+
+    let text_start = String::from_utf8_lossy(&data[..data.len().min(64)]).to_uppercase();
+    if data.starts_with(b"%PDF-") {
+        return "application/pdf";
+    } else if data.starts_with(b"caff") {
+        return "audio/x-caf";
+    } else if data.starts_with(b"ID3") || data.starts_with(&[0xFF, 0xFB]) || data.starts_with(&[0xFF, 0xF3]) {
+        return "audio/mpeg";
+    } else if data.starts_with(b"OggS") {
+        return "audio/ogg";
+    } else if data.len() >= 12 && &data[0..4] == b"RIFF" && &data[8..12] == b"WAVE" {
+        return "audio/wav";
+    } else if data.len() >= 12 && &data[4..8] == b"ftyp" && matches!(&data[8..12], b"M4A " | b"M4B ") {
+        return "audio/mp4";
+    } else if text_start.trim_start().starts_with("BEGIN:VCARD") {
+        return "text/vcard";
+    } else if text_start.trim_start().starts_with("BEGIN:VCALENDAR") {
+        return "text/calendar";
+    }
+
+    let extension = filename
+        .rsplit_once('.')
+        .map(|(_, ext)| ext.to_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "jpg" | "jpeg" => "image/jpeg",
+        "png" => "image/png",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "heic" => "image/heic",
+        "pdf" => "application/pdf",
+        "mp3" => "audio/mpeg",
+        "m4a" => "audio/mp4",
+        "caf" => "audio/x-caf",
+        "wav" => "audio/wav",
+        "ogg" | "opus" => "audio/ogg",
+        "mp4" => "video/mp4",
+        "mov" => "video/quicktime",
+        "vcf" => "text/vcard",
+        "ics" => "text/calendar",
+        "txt" => "text/plain",
+        "md" => "text/markdown",
+        "csv" => "text/csv",
+        "json" => "application/json",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Checks the attachment's declared size so oversize files are never downloaded.
+pub fn check_attachment_size(attachment: &BlueBubblesAttachment, max_bytes: i64) -> Result<()> {
+    match attachment.total_bytes {
+        Some(size) if size > max_bytes => Err(anyhow::anyhow!(
+            "Attachment {} is too large ({} bytes, limit {})",
+            attachment.guid,
+            size,
+            max_bytes
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Prepares images for vision: converts formats the API can't read and
+/// downscales large photos.
+#[derive(Debug, Clone)]
+pub struct ImagePipeline {
+    max_dimension: u32,
+    convert_command: Option<String>,
+}
+
+impl ImagePipeline {
+    pub fn new(max_dimension: u32, convert_command: Option<String>) -> Self {
+        Self {
+            max_dimension,
+            convert_command,
+        }
+    }
+
+    pub fn from_config(config: &Config) -> Self {
+        Self::new(config.vision_max_dimension, config.image_convert_command.clone())
+    }
+
+    pub async fn prepare(&self, data: Vec<u8>, mime_type: Option<&str>) -> Result<VisionImage> {
+        let format = ImageFormat::detect(&data, mime_type)
+            .ok_or_else(|| anyhow::anyhow!("Unrecognised image format ({:?})", mime_type))?;
+
+        debug!("Preparing {} image ({} bytes)", format.mime_type(), data.len());
+
+        let (data, format) = if format.is_vision_supported() {
+            (data, format)
+        } else {
+            let converted = self.convert_to_jpeg(&data, format).await?;
+            info!(
+                "Converted {} image to JPEG ({} -> {} bytes)",
+                format.mime_type(),
+                data.len(),
+                converted.len()
+            );
+            (converted, ImageFormat::Jpeg)
+        };
+
+        let max_dimension = self.max_dimension;
+        tokio::task::spawn_blocking(move || downscale(data, format, max_dimension))
+            .await
+            .context("Image processing task panicked")?
+    }
+
+    /// Readies a prepared image for the image edit endpoint, re-encoding
+    /// formats it rejects (GIF) as PNG.
+    pub async fn prepare_for_edit(&self, image: VisionImage) -> Result<VisionImage> {
+        let format = ImageFormat::detect(&image.data, Some(&image.mime_type))
+            .ok_or_else(|| anyhow::anyhow!("Unrecognised image format ({})", image.mime_type))?;
+        if format.is_edit_supported() {
+            return Ok(image);
+        }
+
+        let converted = tokio::task::spawn_blocking(move || to_png(&image.data, format))
+            .await
+            .context("Image processing task panicked")??;
+        info!("Converted {} image to PNG for editing", format.mime_type());
+        Ok(converted)
+    }
+
+    async fn convert_to_jpeg(&self, data: &[u8], format: ImageFormat) -> Result<Vec<u8>> {
+        let template = self.convert_command.as_ref().ok_or_else(|| {
+            anyhow::anyhow!("No IMAGE_CONVERT_COMMAND configured to convert {}", format.mime_type())
+        })?;
+
+        convert_file(template, data, "heic", "jpg").await
+    }
+}
+
+/// Audio ready to send to a transcription API.
+#[derive(Debug, Clone)]
+pub struct PreparedAudio {
+    pub data: Vec<u8>,
+    pub filename: String,
+    pub mime_type: String,
+}
+
+/// Prepares voice memos for transcription, converting CAF (which
+/// transcription APIs reject) to M4A.
+#[derive(Debug, Clone)]
+pub struct AudioPipeline {
+    convert_command: Option<String>,
+}
+
+impl AudioPipeline {
+    pub fn new(convert_command: Option<String>) -> Self {
+        Self { convert_command }
+    }
+
+    pub fn from_config(config: &Config) -> Self {
+        Self::new(config.audio_convert_command.clone())
+    }
+
+    pub async fn prepare(&self, data: Vec<u8>, filename: Option<&str>) -> Result<PreparedAudio> {
+        let filename = filename.unwrap_or("voice-memo");
+        let mime_type = sniff_mime_type(&data, filename);
+
+        if mime_type != "audio/x-caf" {
+            return Ok(PreparedAudio {
+                filename: format!("audio.{}", extension_for_mime_type(mime_type)),
+                mime_type: mime_type.to_string(),
+                data,
+            });
+        }
+
+        let template = self
+            .convert_command
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("No AUDIO_CONVERT_COMMAND configured to convert CAF audio"))?;
+        let converted = convert_file(template, &data, "caf", "m4a").await?;
+        info!("Converted CAF voice memo to M4A ({} -> {} bytes)", data.len(), converted.len());
+
+        Ok(PreparedAudio {
+            data: converted,
+            filename: "audio.m4a".to_string(),
+            mime_type: "audio/mp4".to_string(),
+        })
+    }
+}
+
+/// Converts `data` with an external command via temporary files.
+async fn convert_file(template: &str, data: &[u8], input_ext: &str, output_ext: &str) -> Result<Vec<u8>> {
+    let id = uuid::Uuid::new_v4();
+    let input = std::env::temp_dir().join(format!("ai-bot-{}.{}", id, input_ext));
+    let output = std::env::temp_dir().join(format!("ai-bot-{}.{}", id, output_ext));
+
+    tokio::fs::write(&input, data)
+        .await
+        .context("Failed to write file for conversion")?;
+    let result = run_convert_command(template, &input, &output).await;
+    let converted = match result {
+        Ok(()) => tokio::fs::read(&output)
+            .await
+            .context("Failed to read converted file"),
+        Err(e) => Err(e),
+    };
+
+    tokio::fs::remove_file(&input).await.ok();
+    tokio::fs::remove_file(&output).await.ok();
+    converted
+}
+
+/// Runs a conversion command template, substituting `{input}` and `{output}`.
+pub async fn run_convert_command(template: &str, input: &Path, output: &Path) -> Result<()> {
+    let mut parts = template.split_whitespace().map(|part| {
+        part.replace("{input}", &input.to_string_lossy())
+            .replace("{output}", &output.to_string_lossy())
+    });
+    let program = parts
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("Conversion command is empty"))?;
+
+    let result = Command::new(&program)
+        .args(parts)
+        .output()
+        .await
+        .with_context(|| format!("Failed to run conversion command '{}'", program))?;
+
+    if !result.status.success() {
+        return Err(anyhow::anyhow!(
+            "Conversion command '{}' failed: {}",
+            program,
+            String::from_utf8_lossy(&result.stderr).trim()
+        ));
+    }
+
+    Ok(())
+}
+
+/// Returns the size that fits within `max_dimension` while keeping the aspect
+/// ratio, or `None` if the image is already small enough.
+pub fn scaled_dimensions(width: u32, height: u32, max_dimension: u32) -> Option<(u32, u32)> {
+    let longest = width.max(height);
+    if longest <= max_dimension || max_dimension == 0 {
+        return None;
+    }
+
+    let scale = max_dimension as f64 / longest as f64;
+    let scaled_width = ((width as f64 * scale).round() as u32).max(1);
+    let scaled_height = ((height as f64 * scale).round() as u32).max(1);
+    Some((scaled_width, scaled_height))
+}
+
+fn downscale(data: Vec<u8>, format: ImageFormat, max_dimension: u32) -> Result<VisionImage> {
+    let codec = format
+        .codec()
+        .ok_or_else(|| anyhow::anyhow!("Cannot decode {}", format.mime_type()))?;
+    let decoded = image::load_from_memory_with_format(&data, codec)
+        .context("Failed to decode image")?;
+
+    let (width, height) = decoded.dimensions();
+    let Some((new_width, new_height)) = scaled_dimensions(width, height, max_dimension) else {
+        return Ok(VisionImage {
+            data,
+            mime_type: format.mime_type().to_string(),
+        });
+    };
+
+    debug!(
+        "Downscaling image from {}x{} to {}x{}",
+        width, height, new_width, new_height
+    );
+    let resized = decoded.resize(new_width, new_height, FilterType::Triangle);
+    encode(resized, format)
+}
+
+fn to_png(data: &[u8], format: ImageFormat) -> Result<VisionImage> {
+    let codec = format
+        .codec()
+        .ok_or_else(|| anyhow::anyhow!("Cannot decode {}", format.mime_type()))?;
+    let decoded = image::load_from_memory_with_format(data, codec).context("Failed to decode image")?;
+
+    let mut buffer = Cursor::new(Vec::new());
+    decoded
+        .write_to(&mut buffer, CodecFormat::Png)
+        .context("Failed to encode image")?;
+    Ok(VisionImage {
+        data: buffer.into_inner(),
+        mime_type: ImageFormat::Png.mime_type().to_string(),
+    })
+}
+
+fn encode(image: DynamicImage, source_format: ImageFormat) -> Result<VisionImage> {
+    // Keep PNG for images with transparency, JPEG for everything else
+    let target = if source_format == ImageFormat::Png && image.color().has_alpha() {
+        ImageFormat::Png
+    } else {
+        ImageFormat::Jpeg
+    };
+
+    let image = if target == ImageFormat::Jpeg {
+        DynamicImage::ImageRgb8(image.to_rgb8())
+    } else {
+        image
+    };
+
+    let mut buffer = Cursor::new(Vec::new());
+    image
+        .write_to(&mut buffer, target.codec().expect("target format has a codec"))
+        .context("Failed to encode image")?;
+
+    Ok(VisionImage {
+        data: buffer.into_inner(),
+        mime_type: target.mime_type().to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detects_format_from_bytes() {
+        assert_eq!(ImageFormat::detect(&[0xFF, 0xD8, 0xFF, 0xE0], None), Some(ImageFormat::Jpeg));
+        assert_eq!(ImageFormat::detect(b"\x89PNG\r\n\x1a\n....", Some("image/jpeg")), Some(ImageFormat::Png));
+        assert_eq!(ImageFormat::detect(b"RIFF\0\0\0\0WEBPVP8 ", None), Some(ImageFormat::Webp));
+        assert_eq!(ImageFormat::detect(b"\0\0\0\x18ftypheic\0\0\0\0", None), Some(ImageFormat::Heic));
+        assert_eq!(
+            ImageFormat::detect(b"\0\0\0\x18ftypmif1\0\0\0\0mif1heic", None),
+            Some(ImageFormat::Heic)
+        );
+        assert_eq!(ImageFormat::detect(b"\0\0\0\x18ftypmif1\0\0\0\0mif1avif", None), None);
+    }
+
+    #[test]
+    fn test_falls_back_to_mime_type() {
+        assert_eq!(ImageFormat::detect(b"????", Some("image/HEIC")), Some(ImageFormat::Heic));
+        assert_eq!(ImageFormat::detect(b"????", Some("application/pdf")), None);
+        assert_eq!(ImageFormat::detect(b"????", None), None);
+    }
+
+    #[test]
+    fn test_sniff_mime_type() {
+        assert_eq!(sniff_mime_type(b"\x89PNG\r\n\x1a\n....", "generated-image.jpg"), "image/png");
+        assert_eq!(sniff_mime_type(b"%PDF-1.7", "file"), "application/pdf");
+        assert_eq!(sniff_mime_type(b"BEGIN:VCARD\r\nVERSION:3.0", "contact"), "text/vcard");
+        assert_eq!(sniff_mime_type(b"BEGIN:VCALENDAR\r\n", "event.txt"), "text/calendar");
+        assert_eq!(sniff_mime_type(b"ID3\x04", "voice"), "audio/mpeg");
+        assert_eq!(sniff_mime_type(b"hello", "notes.md"), "text/markdown");
+        assert_eq!(sniff_mime_type(b"\0\0", "blob"), "application/octet-stream");
+    }
+
+    #[test]
+    fn test_safe_filename() {
+        assert_eq!(safe_filename("notes.txt").as_deref(), Some("notes.txt"));
+        assert_eq!(safe_filename("../../etc/passwd").as_deref(), Some("passwd"));
+        assert_eq!(safe_filename("C:\\Users\\me\\list.csv").as_deref(), Some("list.csv"));
+        assert_eq!(safe_filename("bad\nname\u{7}.md").as_deref(), Some("badname.md"));
+        assert_eq!(safe_filename(".hidden").as_deref(), Some("hidden"));
+        assert_eq!(safe_filename("dir/"), None);
+        assert_eq!(safe_filename(" .. "), None);
+
+        let long = safe_filename(&format!("{}.txt", "a".repeat(300))).unwrap();
+        assert_eq!(long.chars().count(), MAX_FILENAME_CHARS);
+        assert!(long.ends_with("a.txt"));
+    }
+
+    #[test]
+    fn test_scaled_dimensions() {
+        assert_eq!(scaled_dimensions(1000, 800, 2048), None);
+        assert_eq!(scaled_dimensions(4032, 3024, 2048), Some((2048, 1536)));
+        assert_eq!(scaled_dimensions(3024, 4032, 1024), Some((768, 1024)));
+    }
+
+    #[tokio::test]
+    async fn test_prepare_downscales_large_images() {
+        let image = DynamicImage::new_rgb8(300, 100);
+        let mut buffer = Cursor::new(Vec::new());
+        image.write_to(&mut buffer, CodecFormat::Png).unwrap();
+
+        let pipeline = ImagePipeline::new(150, None);
+        let prepared = pipeline.prepare(buffer.into_inner(), Some("image/png")).await.unwrap();
+
+        assert_eq!(prepared.mime_type, "image/jpeg");
+        let decoded = image::load_from_memory(&prepared.data).unwrap();
+        assert_eq!(decoded.dimensions(), (150, 50));
+    }
+
+    #[tokio::test]
+    async fn test_prepare_for_edit_converts_gifs_to_png() {
+        let mut buffer = Cursor::new(Vec::new());
+        DynamicImage::new_rgba8(20, 10)
+            .write_to(&mut buffer, CodecFormat::Gif)
+            .unwrap();
+        let gif = VisionImage {
+            data: buffer.into_inner(),
+            mime_type: "image/gif".to_string(),
+        };
+
+        let pipeline = ImagePipeline::new(2048, None);
+        let prepared = pipeline.prepare_for_edit(gif).await.unwrap();
+        assert_eq!(prepared.mime_type, "image/png");
+        assert_eq!(ImageFormat::detect(&prepared.data, None), Some(ImageFormat::Png));
+        assert_eq!(image::load_from_memory(&prepared.data).unwrap().dimensions(), (20, 10));
+
+        let jpeg = VisionImage {
+            data: vec![0xFF, 0xD8, 0xFF, 0xE0],
+            mime_type: "image/jpeg".to_string(),
+        };
+        assert_eq!(pipeline.prepare_for_edit(jpeg).await.unwrap().data, vec![0xFF, 0xD8, 0xFF, 0xE0]);
+    }
+
+    #[test]
+    fn test_check_size_uses_total_bytes() {
+        let mut attachment = BlueBubblesAttachment {
+            guid: "a".to_string(),
+            original_rowid: None,
+            mime_type: Some("image/jpeg".to_string()),
+            transfer_name: None,
+            total_bytes: Some(999),
+        };
+        assert!(check_attachment_size(&attachment, 1000).is_ok());
+
+        attachment.total_bytes = Some(5000);
+        assert!(check_attachment_size(&attachment, 1000).is_err());
+    }
+}
@@ -0,0 +1,310 @@
+use anyhow::Result;
+use prometheus::{
+    Encoder, Histogram, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge, Opts,
+    Registry, TextEncoder,
+};
+use std::future::Future;
+use std::sync::{Arc, LazyLock};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::Mutex;
+use tracing::{debug, error, info};
+
+use crate::bluebubbles::BlueBubblesClient;
+use crate::database::Database;
+
+/// Longest request head the metrics server reads; scrapers send far less.
+const MAX_REQUEST_BYTES: usize = 8 * 1024;
+
+/// How long a client gets to send its request, and each health check to answer.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long a health check result is reused, so frequent probes don't each
+/// hit BlueBubbles and the database.
+const HEALTH_CACHE_TTL: Duration = Duration::from_secs(5);
+
+/// Everything the bot exports to Prometheus. Shared process-wide so chat
+/// agents and their AI clients can record without being handed a registry.
+pub struct Metrics {
+    registry: Registry,
+    pub poll_duration: Histogram,
+    pub poll_errors: IntCounter,
+    pub queue_depth: IntGauge,
+    pub messages_processed: IntCounterVec,
+    pub provider_duration: HistogramVec,
+    pub provider_errors: IntCounterVec,
+    pub chat_agents: IntGauge,
+}
+
+static METRICS: LazyLock<Metrics> =
+    LazyLock::new(|| Metrics::new().expect("metric definitions are valid"));
+
+pub fn metrics() -> &'static Metrics {
+    &METRICS
+}
+
+impl Metrics {
+    fn new() -> Result<Self> {
+        let registry = Registry::new();
+
+        let poll_duration = Histogram::with_opts(HistogramOpts::new(
+            "bot_poll_duration_seconds",
+            "Time taken to poll BlueBubbles for new messages",
+        ))?;
+        let poll_errors = IntCounter::new("bot_poll_errors_total", "Polls of BlueBubbles that failed")?;
+        let queue_depth = IntGauge::new("bot_queue_depth", "Messages waiting in the queue")?;
+        let messages_processed = IntCounterVec::new(
+            Opts::new("bot_messages_processed_total", "Queued messages handled, by outcome"),
+            &["outcome"],
+        )?;
+        let provider_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "bot_provider_request_duration_seconds",
+                "Time taken by requests to AI providers",
+            )
+            .buckets(vec![0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0]),
+            &["provider", "operation"],
+        )?;
+        let provider_errors = IntCounterVec::new(
+            Opts::new("bot_provider_errors_total", "Failed requests to AI providers"),
+            &["provider", "operation"],
+        )?;
+        let chat_agents = IntGauge::new("bot_chat_agents", "Chat agents currently running")?;
+
+        registry.register(Box::new(poll_duration.clone()))?;
+        registry.register(Box::new(poll_errors.clone()))?;
+        registry.register(Box::new(queue_depth.clone()))?;
+        registry.register(Box::new(messages_processed.clone()))?;
+        registry.register(Box::new(provider_duration.clone()))?;
+        registry.register(Box::new(provider_errors.clone()))?;
+        registry.register(Box::new(chat_agents.clone()))?;
+
+        Ok(Self {
+            registry,
+            poll_duration,
+            poll_errors,
+            queue_depth,
+            messages_processed,
+            provider_duration,
+            provider_errors,
+            chat_agents,
+        })
+    }
+
+    /// Counts a queued message by what became of it: "delivered",
+    /// "throttled" or "failed".
+    pub fn record_message(&self, outcome: &str) {
+        self.messages_processed.with_label_values(&[outcome]).inc();
+    }
+
+    /// Records how long a request to an AI provider took and whether it failed.
+    pub fn record_provider(&self, provider: &str, operation: &str, elapsed: Duration, failed: bool) {
+        self.provider_duration
+            .with_label_values(&[provider, operation])
+            .observe(elapsed.as_secs_f64());
+        if failed {
+            self.provider_errors
+                .with_label_values(&[provider, operation])
+                .inc();
+        }
+    }
+
+    /// The metrics in Prometheus' text format.
+    pub fn render(&self) -> Result<String> {
+        let mut buffer = Vec::new();
+        TextEncoder::new().encode(&self.registry.gather(), &mut buffer)?;
+        Ok(String::from_utf8(buffer)?)
+    }
+}
+
+/// Times a request to an AI provider and counts it as an error if it fails.
+pub async fn observe_provider<T>(
+    provider: &str,
+    operation: &str,
+    request: impl Future<Output = Result<T>>,
+) -> Result<T> {
+    let started = Instant::now();
+    let result = request.await;
+    metrics().record_provider(provider, operation, started.elapsed(), result.is_err());
+    result
+}
+
+/// The last health check and when it ran.
+type HealthCache = Arc<Mutex<Option<(Instant, bool, serde_json::Value)>>>;
+
+/// Serves `/metrics` and `/healthz` until the process exits. The health
+/// check answers 503 when BlueBubbles or the database can't be reached.
+/// Neither endpoint asks for credentials, so keep the address on loopback or
+/// a private network.
+pub async fn serve(listener: TcpListener, database: Database, bluebubbles: BlueBubblesClient) {
+    if let Ok(addr) = listener.local_addr() {
+        info!("Serving metrics and health checks on http://{}", addr);
+    }
+
+    let health = HealthCache::default();
+
+    loop {
+        let (stream, peer) = match listener.accept().await {
+            Ok(connection) => connection,
+            Err(e) => {
+                error!("Failed to accept metrics connection: {}", e);
+                continue;
+            }
+        };
+
+        let database = database.clone();
+        let bluebubbles = bluebubbles.clone();
+        let health = health.clone();
+        tokio::spawn(async move {
+            if let Err(e) = handle_connection(stream, &database, &bluebubbles, &health).await {
+                debug!("Metrics request from {} failed: {}", peer, e);
+            }
+        });
+    }
+}
+
+async fn handle_connection(
+    mut stream: TcpStream,
+    database: &Database,
+    bluebubbles: &BlueBubblesClient,
+    health: &HealthCache,
+) -> Result<()> {
+    let head = tokio::time::timeout(REQUEST_TIMEOUT, read_request_head(&mut stream)).await??;
+
+    let (status, content_type, body) = match parse_request_line(&head) {
+        Some(("GET", "/metrics")) => match metrics().render() {
+            Ok(body) => ("200 OK", "text/plain; version=0.0.4", body),
+            Err(e) => ("500 Internal Server Error", "text/plain", e.to_string()),
+        },
+        Some(("GET", "/healthz")) => {
+            let (healthy, body) = cached_health(health, database, bluebubbles).await;
+            let status = if healthy { "200 OK" } else { "503 Service Unavailable" };
+            (status, "application/json", body.to_string())
+        }
+        Some(("GET", _)) => ("404 Not Found", "text/plain", "Not found".to_string()),
+        Some(_) => ("405 Method Not Allowed", "text/plain", "Method not allowed".to_string()),
+        None => ("400 Bad Request", "text/plain", "Bad request".to_string()),
+    };
+
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
+
+async fn read_request_head(stream: &mut TcpStream) -> Result<String> {
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 1024];
+    while !buffer.windows(4).any(|window| window == b"\r\n\r\n") && buffer.len() < MAX_REQUEST_BYTES {
+        let read = stream.read(&mut chunk).await?;
+        if read == 0 {
+            break;
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+    }
+    Ok(String::from_utf8_lossy(&buffer).into_owned())
+}
+
+/// The method and path of an HTTP request, without any query string.
+fn parse_request_line(head: &str) -> Option<(&str, &str)> {
+    let mut parts = head.lines().next()?.split_whitespace();
+    let method = parts.next()?;
+    let target = parts.next()?;
+    parts.next().filter(|version| version.starts_with("HTTP/"))?;
+
+    let path = target.split('?').next().unwrap_or(target);
+    Some((method, path))
+}
+
+/// The latest health check, running a new one if the last is too old.
+/// Concurrent probes wait for the same check rather than starting their own.
+async fn cached_health(
+    health: &HealthCache,
+    database: &Database,
+    bluebubbles: &BlueBubblesClient,
+) -> (bool, serde_json::Value) {
+    let mut last = health.lock().await;
+    if let Some((_, healthy, body)) = last
+        .as_ref()
+        .filter(|(checked_at, _, _)| checked_at.elapsed() < HEALTH_CACHE_TTL)
+    {
+        return (*healthy, body.clone());
+    }
+
+    let (healthy, body) = check_health(database, bluebubbles).await;
+    *last = Some((Instant::now(), healthy, body.clone()));
+    (healthy, body)
+}
+
+async fn check_health(database: &Database, bluebubbles: &BlueBubblesClient) -> (bool, serde_json::Value) {
+    let (database_check, bluebubbles_check) = tokio::join!(
+        tokio::time::timeout(REQUEST_TIMEOUT, database.ping()),
+        tokio::time::timeout(REQUEST_TIMEOUT, bluebubbles.ping()),
+    );
+
+    let database_status = describe_check(database_check);
+    let bluebubbles_status = describe_check(bluebubbles_check);
+    let healthy = database_status == "ok" && bluebubbles_status == "ok";
+
+    let body = serde_json::json!({
+        "status": if healthy { "ok" } else { "unavailable" },
+        "database": database_status,
+        "bluebubbles": bluebubbles_status,
+    });
+    (healthy, body)
+}
+
+fn describe_check(result: Result<Result<()>, tokio::time::error::Elapsed>) -> String {
+    match result {
+        Ok(Ok(())) => "ok".to_string(),
+        Ok(Err(e)) => e.to_string(),
+        Err(_) => "timed out".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_request_line() {
+        assert_eq!(
+            parse_request_line("GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n"),
+            Some(("GET", "/metrics"))
+        );
+        assert_eq!(parse_request_line("GET /healthz?verbose=1 HTTP/1.0\r\n"), Some(("GET", "/healthz")));
+        assert_eq!(parse_request_line("POST /metrics HTTP/1.1\r\n"), Some(("POST", "/metrics")));
+        assert_eq!(parse_request_line("hello there"), None);
+        assert_eq!(parse_request_line(""), None);
+    }
+
+    #[test]
+    fn test_record_provider_counts_errors() {
+        let metrics = Metrics::new().unwrap();
+        metrics.record_provider("test", "ok", Duration::from_millis(20), false);
+        metrics.record_provider("test", "failing", Duration::from_millis(20), true);
+
+        assert_eq!(metrics.provider_errors.with_label_values(&["test", "failing"]).get(), 1);
+        assert_eq!(metrics.provider_errors.with_label_values(&["test", "ok"]).get(), 0);
+
+        let text = metrics.render().unwrap();
+        assert!(text.contains("bot_provider_request_duration_seconds_count{operation=\"ok\",provider=\"test\"} 1"));
+        assert!(text.contains("bot_queue_depth 0"));
+    }
+
+    #[tokio::test]
+    async fn test_observe_provider_passes_results_through() {
+        let ok: Result<i32> = observe_provider("test", "ok", async { Ok(7) }).await;
+        assert_eq!(ok.unwrap(), 7);
+        let failed: Result<()> =
+            observe_provider("test", "failing", async { Err(anyhow::anyhow!("boom")) }).await;
+        assert_eq!(failed.unwrap_err().to_string(), "boom");
+    }
+}
@@ -1,24 +1,76 @@
 use anyhow::{Context, Result};
+use chrono::{DateTime, Timelike, Utc};
 use dashmap::DashMap;
-use std::collections::HashSet;
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
-use tokio::{sync::mpsc, time::interval};
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::{net::TcpListener, sync::mpsc, time::interval};
 use tracing::{debug, error, info, warn};
 
 use crate::{
     bluebubbles::BlueBubblesClient,
     chat_agent::{ChatAgent, ChatAgentHandle},
-    config::Config,
+    commands::{Command, CommandParser},
+    config::{ChatPolicy, Config, IntentClassifier},
     database::Database,
-    types::QueuedMessage,
+    limits::{RateLimiter, Throttle},
+    metrics::{self, metrics},
+    polls::{self, counts_as_vote, parse_vote, poll_id_from_text, Tapback},
+    scheduling::{next_run, resolve_timezone, start_of_day},
+    triggers::TriggerSet,
+    types::{AmbientMessage, BlueBubblesAttachment, ChatConfig, QueuedMessage},
 };
 
+/// Images posted this long before a trigger are treated as part of it, so
+/// "send a photo, then ask about it" works.
+const PENDING_IMAGE_WINDOW_MS: u64 = 5 * 60 * 1000;
+
+/// Overheard messages kept per chat for proactive mode.
+const MAX_AMBIENT_MESSAGES: usize = 20;
+
+/// Times a reminder is tried before it's marked failed.
+const MAX_REMINDER_ATTEMPTS: i64 = 5;
+
+/// The last person the bot answered in a chat, so their next message within
+/// the chat's follow-up window doesn't need a trigger.
+struct FollowUp {
+    sender: String,
+    last_activity: u64,
+}
+
+/// Whether a message is meant for the bot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Addressed {
+    No,
+    Yes,
+    /// Mentioned mid-sentence: the chat's agent asks the intent classifier,
+    /// and the message only counts against the rate limits if it says yes
+    Maybe,
+}
+
+/// A chat's compiled triggers, along with the settings they were built from
+/// so they're only rebuilt when those change.
+struct CachedTriggers {
+    trigger_name: String,
+    triggers: Vec<String>,
+    set: TriggerSet,
+}
+
 pub struct BotOrchestrator {
     config: Config,
     database: Database,
     bluebubbles: BlueBubblesClient,
     chat_agents: DashMap<String, ChatAgentHandle>,
     processed_messages: HashSet<String>,
+    pending_images: HashMap<String, Vec<(u64, BlueBubblesAttachment)>>,
+    bot_messages: HashSet<String>,
+    other_messages: HashSet<String>,
+    follow_ups: HashMap<String, FollowUp>,
+    global_triggers: Vec<String>,
+    trigger_sets: HashMap<String, CachedTriggers>,
+    ambient_messages: HashMap<String, Vec<AmbientMessage>>,
+    last_proactive_check: HashMap<String, Instant>,
+    rate_limiter: RateLimiter,
+    command_parser: CommandParser,
     startup_time: u64,
 }
 
@@ -33,6 +85,13 @@ impl BotOrchestrator {
             config.bluebubbles_password.clone(),
         );
 
+        let rate_limiter = RateLimiter::new(
+            config.sender_messages_per_minute,
+            config.chat_messages_per_minute,
+        );
+
+        let global_triggers = config.triggers();
+
         let startup_time = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .expect("Time went backwards")
@@ -44,6 +103,16 @@ impl BotOrchestrator {
             bluebubbles,
             chat_agents: DashMap::new(),
             processed_messages: HashSet::new(),
+            pending_images: HashMap::new(),
+            bot_messages: HashSet::new(),
+            other_messages: HashSet::new(),
+            follow_ups: HashMap::new(),
+            global_triggers,
+            trigger_sets: HashMap::new(),
+            ambient_messages: HashMap::new(),
+            last_proactive_check: HashMap::new(),
+            rate_limiter,
+            command_parser: CommandParser::new()?,
             startup_time,
         })
     }
@@ -51,22 +120,50 @@ impl BotOrchestrator {
     pub async fn run(&mut self) -> Result<()> {
         info!("Starting bot orchestrator");
 
+        if let Some(addr) = &self.config.metrics_addr {
+            let listener = TcpListener::bind(addr)
+                .await
+                .with_context(|| format!("Failed to bind metrics server to {}", addr))?;
+            tokio::spawn(metrics::serve(listener, self.database.clone(), self.bluebubbles.clone()));
+        }
+
         let mut poll_interval = interval(Duration::from_secs(3));
         let mut queue_interval = interval(Duration::from_millis(500)); // Process queue more frequently
         let mut cleanup_interval = interval(Duration::from_secs(300)); // 5 minutes
+        let mut proactive_interval = interval(Duration::from_secs(60));
+        let mut scheduler_interval = interval(Duration::from_secs(30));
 
         loop {
             tokio::select! {
                 _ = poll_interval.tick() => {
+                    let started = Instant::now();
                     if let Err(e) = self.poll_and_process_messages().await {
                         error!("Error during message polling: {}", e);
+                        metrics().poll_errors.inc();
                     }
+                    metrics().poll_duration.observe(started.elapsed().as_secs_f64());
                 }
                 _ = queue_interval.tick() => {
                     if let Err(e) = self.process_message_queue().await {
                         error!("Error during queue processing: {}", e);
                     }
                 }
+                _ = proactive_interval.tick() => {
+                    if let Err(e) = self.run_proactive_checks().await {
+                        error!("Error during proactive checks: {}", e);
+                    }
+                }
+                _ = scheduler_interval.tick() => {
+                    if let Err(e) = self.send_scheduled_messages().await {
+                        error!("Error sending scheduled messages: {}", e);
+                    }
+                    if let Err(e) = self.run_recurring_jobs().await {
+                        error!("Error running recurring jobs: {}", e);
+                    }
+                    if let Err(e) = self.close_expired_polls().await {
+                        error!("Error closing expired polls: {}", e);
+                    }
+                }
                 _ = cleanup_interval.tick() => {
                     if let Err(e) = self.cleanup().await {
                         error!("Error during cleanup: {}", e);
@@ -84,7 +181,7 @@ impl BotOrchestrator {
     }
 
     async fn poll_and_process_messages(&mut self) -> Result<()> {
-        info!("Polling for new messages");
+        debug!("Polling for new messages");
 
         let chats = self
             .bluebubbles
@@ -93,11 +190,37 @@ impl BotOrchestrator {
             .context("Failed to get chats from BlueBubbles")?;
 
         for chat in chats {
+            if !self.config.chat_allowed(&chat.guid) {
+                continue;
+            }
+
+            let policy = self.config.chat_policy(&chat.guid);
+            if policy == ChatPolicy::Off {
+                continue;
+            }
+
             let messages = self
                 .bluebubbles
                 .get_messages_after(&chat.guid, Some(self.startup_time))
                 .await
                 .context("Failed to get messages from BlueBubbles")?;
+            if messages.iter().all(|message| self.processed_messages.contains(&message.guid)) {
+                continue;
+            }
+
+            let chat_config = self
+                .database
+                .get_chat_config(&chat.guid)
+                .await?
+                .unwrap_or_else(|| ChatConfig::new(chat.guid.clone()));
+            let paused = chat_config.paused;
+            let proactive = chat_config.proactive;
+            // Paused chats don't take votes
+            let mut open_poll = if paused {
+                None
+            } else {
+                self.database.get_open_poll(&chat.guid).await?
+            };
 
             for message in messages.into_iter().rev() {
                 // Process in chronological order
@@ -106,16 +229,29 @@ impl BotOrchestrator {
                     continue;
                 }
 
-                // Skip messages from us
+                let message_time =
+                    message.date_created.or(message.date_delivered).unwrap_or(0) as u64;
+
+                // Skip messages from us, remembering them so replies to the bot count as triggers
                 if message.is_from_me == Some(true) {
+                    // Remember which message is the poll, so tapbacks on it count as votes
+                    if let Some(poll_id) = message.text.as_deref().and_then(poll_id_from_text) {
+                        self.database
+                            .set_poll_message_guid(poll_id, &message.guid)
+                            .await?;
+                        if let Some(poll) = open_poll.as_mut().filter(|poll| poll.id == poll_id) {
+                            poll.message_guid = Some(message.guid.clone());
+                        }
+                    }
+                    if let Some(follow_up) = self.follow_ups.get_mut(&chat.guid) {
+                        follow_up.last_activity = follow_up.last_activity.max(message_time);
+                    }
+                    self.bot_messages.insert(message.guid.clone());
                     self.processed_messages.insert(message.guid);
                     continue;
                 }
-                //
-                // Skip messages older than startup time
-                let message_time =
-                    message.date_created.or(message.date_delivered).unwrap_or(0) as u64;
 
+                // Skip messages older than startup time
                 if message_time < self.startup_time {
                     self.processed_messages.insert(message.guid);
                     continue;
@@ -127,20 +263,75 @@ impl BotOrchestrator {
                     continue;
                 }
 
-                let text = message.text.unwrap_or_default();
-                if text.is_empty() {
+                let sender = message.sender().map(|sender| sender.to_string());
+                if !self.config.handle_allowed(sender.as_deref()) {
+                    debug!("Ignoring message from {:?} in chat {}", sender, chat.guid);
                     self.processed_messages.insert(message.guid);
                     continue;
                 }
 
-                debug!("Processing message from chat {}: '{}'", chat.guid, text);
+                if let Some(tapback) = Tapback::from_message(&message).filter(|_| !paused) {
+                    if self.record_tapback_vote(&tapback, sender.as_deref()).await? {
+                        self.processed_messages.insert(message.guid);
+                        continue;
+                    }
+                }
 
-                // Check for triggers - both @ commands and NLP triggers
-                let contains_trigger = self.check_message_triggers(&chat.guid, &text).await?;
+                let media_attachments = self.media_attachments(message.attachments.unwrap_or_default());
+                let has_audio = media_attachments
+                    .iter()
+                    .any(|attachment| self.bluebubbles.is_audio_attachment(attachment));
+
+                // Attachment-only messages carry an object replacement character.
+                // Voice memos go on, since replying with one can continue a conversation.
+                let text = message.text.unwrap_or_default().replace('\u{FFFC}', "");
+                let text = text.trim().to_string();
+                if text.is_empty() && !has_audio {
+                    self.stash_pending_images(&chat.guid, message_time, &media_attachments);
+                    self.processed_messages.insert(message.guid);
+                    continue;
+                }
 
-                debug!("Message contains trigger: {}", contains_trigger);
+                debug!("Processing message from chat {}: '{}'", chat.guid, text);
+
+                // A bare number or option while a poll is open is a vote, if it
+                // replies to the poll or comes soon after it
+                if let (Some(poll), Some(voter)) = (&open_poll, &sender) {
+                    let sent_at = DateTime::from_timestamp_millis(message_time as i64).unwrap_or_else(Utc::now);
+                    let vote = parse_vote(&text, &poll.options).filter(|_| {
+                        counts_as_vote(poll, message.thread_originator_guid.as_deref(), sent_at)
+                    });
+                    if let Some(option) = vote {
+                        info!("Vote for option {} in poll #{} from {}", option + 1, poll.id, voter);
+                        self.database.record_poll_vote(poll.id, voter, option).await?;
+                        self.processed_messages.insert(message.guid);
+                        continue;
+                    }
+                }
 
-                if contains_trigger {
+                // Check for triggers - both @ commands and NLP triggers. A paused
+                // chat only listens for the command that resumes it.
+                let addressed = if paused {
+                    match self.command_parser.parse_command(&text) {
+                        Some(Command::Bot { enabled: true }) => Addressed::Yes,
+                        _ => Addressed::No,
+                    }
+                } else if policy == ChatPolicy::Always {
+                    Addressed::Yes
+                } else {
+                    self.check_message_triggers(
+                        &chat_config,
+                        &text,
+                        sender.as_deref(),
+                        message_time,
+                        message.thread_originator_guid.as_deref(),
+                    )
+                    .await?
+                };
+
+                debug!("Message addressed to the bot: {:?}", addressed);
+
+                if addressed != Addressed::No {
                     info!("Found triggered message in chat {}: {}", chat.guid, text);
 
                     // Mark as processed
@@ -149,11 +340,50 @@ impl BotOrchestrator {
                         .mark_message_processed(&message.guid, &chat.guid)
                         .await?;
 
+                    // A trigger sent as a reply also covers the photo or voice memo it replies to
+                    let mut attachments = media_attachments;
+                    if let Some(originator_guid) = &message.thread_originator_guid {
+                        attachments.extend(self.replied_to_attachments(originator_guid).await);
+                    }
+
+                    // A mention waits on the classifier, so it doesn't pick up
+                    // earlier images, reset what was overheard or open a follow-up
+                    if addressed == Addressed::Yes {
+                        attachments = self.take_pending_images(&chat.guid, message_time, attachments);
+
+                        // The bot is in the conversation now; start overhearing afresh
+                        self.ambient_messages.remove(&chat.guid);
+
+                        if let Some(sender) = &sender {
+                            self.follow_ups.insert(
+                                chat.guid.clone(),
+                                FollowUp {
+                                    sender: sender.clone(),
+                                    last_activity: message_time,
+                                },
+                            );
+                        }
+                    }
+
                     // Queue the message for processing
-                    if let Err(e) = self.database.queue_message(&chat.guid, &text).await {
+                    if let Err(e) = self
+                        .database
+                        .queue_message(
+                            &chat.guid,
+                            sender.as_deref(),
+                            &text,
+                            &attachments,
+                            addressed == Addressed::Maybe,
+                        )
+                        .await
+                    {
                         error!("Failed to queue message for chat {}: {}", chat.guid, e);
                     }
                 } else {
+                    if proactive && !paused && !text.is_empty() {
+                        self.overhear(&chat.guid, sender, text);
+                    }
+                    self.stash_pending_images(&chat.guid, message_time, &media_attachments);
                     self.processed_messages.insert(message.guid);
                 }
             }
@@ -165,41 +395,316 @@ impl BotOrchestrator {
             let keep = messages_vec.into_iter().skip(500).collect();
             self.processed_messages = keep;
         }
+        if self.bot_messages.len() > 1000 {
+            // Forgotten guids are looked up again if someone replies to them
+            self.bot_messages.clear();
+        }
+        if self.other_messages.len() > 1000 {
+            self.other_messages.clear();
+        }
 
         debug!("Finished polling messages");
         Ok(())
     }
 
+    fn overhear(&mut self, chat_guid: &str, sender: Option<String>, text: String) {
+        let messages = self.ambient_messages.entry(chat_guid.to_string()).or_default();
+        messages.push(AmbientMessage { sender, text });
+        if messages.len() > MAX_AMBIENT_MESSAGES {
+            messages.remove(0);
+        }
+    }
+
+    /// Hands overheard conversation to proactive chats' agents so the model
+    /// can decide whether to join in, at most once per interval per chat and
+    /// never during the chat's quiet hours.
+    async fn run_proactive_checks(&mut self) -> Result<()> {
+        let min_interval = Duration::from_secs(self.config.proactive_interval_mins * 60);
+        let ready: Vec<String> = self
+            .ambient_messages
+            .iter()
+            .filter(|(_, messages)| messages.len() >= self.config.proactive_min_messages.max(1))
+            .filter(|(chat_guid, _)| {
+                self.last_proactive_check
+                    .get(*chat_guid)
+                    .is_none_or(|last| last.elapsed() >= min_interval)
+            })
+            .map(|(chat_guid, _)| chat_guid.clone())
+            .collect();
+
+        for chat_guid in ready {
+            if let Some(quiet_hours) = self.config.quiet_hours {
+                let timezone = self
+                    .database
+                    .get_chat_config(&chat_guid)
+                    .await?
+                    .and_then(|chat_config| chat_config.timezone);
+                let tz = resolve_timezone(timezone.as_deref(), &self.config.default_timezone);
+                if quiet_hours.contains(Utc::now().with_timezone(&tz).hour()) {
+                    continue;
+                }
+            }
+
+            let Some(messages) = self.ambient_messages.remove(&chat_guid) else {
+                continue;
+            };
+            self.last_proactive_check.insert(chat_guid.clone(), Instant::now());
+            if !self.chat_within_quota(&chat_guid).await? {
+                continue;
+            }
+
+            debug!(
+                "Checking whether to chime in to chat {} after {} messages",
+                chat_guid,
+                messages.len()
+            );
+            self.ensure_chat_agent(&chat_guid).await?;
+            if let Some(agent_handle) = self.chat_agents.get(&chat_guid) {
+                if let Err(e) = agent_handle.send_ambient(messages).await {
+                    error!("Failed to send ambient messages to agent for {}: {}", chat_guid, e);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Sends reminders whose time has come. Each is marked sent so it only
+    /// goes out once, even if the bot was down when it was due. A failed send
+    /// is retried with growing gaps before the reminder is marked failed.
+    async fn send_scheduled_messages(&self) -> Result<()> {
+        for scheduled in self.database.get_due_scheduled_messages(Utc::now()).await? {
+            let text = format!("⏰ Reminder: {}", scheduled.message);
+            match self.bluebubbles.send_message(&scheduled.chat_guid, &text).await {
+                Ok(_) => {
+                    info!("Sent reminder #{} to chat {}", scheduled.id, scheduled.chat_guid);
+                    self.database
+                        .set_scheduled_message_status(scheduled.id, "sent")
+                        .await?;
+                }
+                Err(e) if scheduled.attempts + 1 < MAX_REMINDER_ATTEMPTS => {
+                    // 1, 2, 4, 8 minutes
+                    let retry_at = Utc::now() + chrono::Duration::minutes(1 << scheduled.attempts);
+                    warn!(
+                        "Failed to send reminder #{} to chat {}, retrying at {}: {}",
+                        scheduled.id, scheduled.chat_guid, retry_at, e
+                    );
+                    self.database
+                        .retry_scheduled_message(scheduled.id, retry_at)
+                        .await?;
+                }
+                Err(e) => {
+                    error!(
+                        "Failed to send reminder #{} to chat {}, giving up: {}",
+                        scheduled.id, scheduled.chat_guid, e
+                    );
+                    self.database
+                        .set_scheduled_message_status(scheduled.id, "failed")
+                        .await?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Hands due recurring jobs to their chat agents. The next run is saved
+    /// first, so a job missed while the bot was down runs once on startup
+    /// rather than once per missed slot.
+    async fn run_recurring_jobs(&mut self) -> Result<()> {
+        let now = Utc::now();
+        for job in self.database.get_due_recurring_jobs(now).await? {
+            let timezone = self
+                .database
+                .get_chat_config(&job.chat_guid)
+                .await?
+                .and_then(|chat_config| chat_config.timezone);
+            let tz = resolve_timezone(timezone.as_deref(), &self.config.default_timezone);
+            match next_run(&job.schedule, tz, now) {
+                Some(next_run_at) => {
+                    self.database
+                        .reschedule_recurring_job(job.id, next_run_at)
+                        .await?
+                }
+                None => {
+                    warn!("Recurring job #{} has no future runs, removing it", job.id);
+                    self.database
+                        .delete_recurring_job(&job.chat_guid, job.id)
+                        .await?;
+                }
+            }
+
+            if !self.chat_within_quota(&job.chat_guid).await? {
+                continue;
+            }
+
+            info!("Running recurring job #{} in chat {}", job.id, job.chat_guid);
+            self.ensure_chat_agent(&job.chat_guid).await?;
+            if let Some(agent_handle) = self.chat_agents.get(&job.chat_guid) {
+                if let Err(e) = agent_handle.run_prompt(job.clone()).await {
+                    error!("Failed to send recurring job to agent for {}: {}", job.chat_guid, e);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Counts a tapback on a poll message as a vote, or takes it back when the
+    /// tapback is removed. Returns false if the tapback wasn't on an open poll.
+    async fn record_tapback_vote(&self, tapback: &Tapback, voter: Option<&str>) -> Result<bool> {
+        let Some(poll) = self.database.get_open_poll_by_message(&tapback.target_guid).await? else {
+            return Ok(false);
+        };
+        let Some(voter) = voter.filter(|_| tapback.option < poll.options.len()) else {
+            // Still a reaction to the poll, not something for the bot to answer
+            return Ok(true);
+        };
+
+        if tapback.removed {
+            info!("Vote for option {} in poll #{} withdrawn by {}", tapback.option + 1, poll.id, voter);
+            self.database
+                .remove_poll_vote(poll.id, voter, tapback.option)
+                .await?;
+        } else {
+            info!("Tapback vote for option {} in poll #{} from {}", tapback.option + 1, poll.id, voter);
+            self.database
+                .record_poll_vote(poll.id, voter, tapback.option)
+                .await?;
+        }
+        Ok(true)
+    }
+
+    /// Closes polls that ran out of time and announces their results.
+    async fn close_expired_polls(&self) -> Result<()> {
+        for poll in self.database.get_expired_polls(Utc::now()).await? {
+            let Some(results) = polls::close_poll(&self.database, &poll).await? else {
+                continue;
+            };
+            info!("Poll #{} in chat {} timed out", poll.id, poll.chat_guid);
+            if let Err(e) = self.bluebubbles.send_message(&poll.chat_guid, &results).await {
+                error!("Failed to announce results of poll #{}: {}", poll.id, e);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Keeps the attachments the bot can use: images and audio.
+    fn media_attachments(&self, attachments: Vec<BlueBubblesAttachment>) -> Vec<BlueBubblesAttachment> {
+        attachments
+            .into_iter()
+            .filter(|attachment| {
+                self.bluebubbles.is_image_attachment(attachment)
+                    || self.bluebubbles.is_audio_attachment(attachment)
+            })
+            .collect()
+    }
+
+    async fn replied_to_attachments(&self, originator_guid: &str) -> Vec<BlueBubblesAttachment> {
+        match self.bluebubbles.get_message(originator_guid).await {
+            Ok(Some(original)) => self.media_attachments(original.attachments.unwrap_or_default()),
+            Ok(None) => Vec::new(),
+            Err(e) => {
+                error!("Failed to fetch replied-to message {}: {}", originator_guid, e);
+                Vec::new()
+            }
+        }
+    }
+
+    fn stash_pending_images(
+        &mut self,
+        chat_guid: &str,
+        message_time: u64,
+        attachments: &[BlueBubblesAttachment],
+    ) {
+        let attachments: Vec<BlueBubblesAttachment> = attachments
+            .iter()
+            .filter(|attachment| self.bluebubbles.is_image_attachment(attachment))
+            .cloned()
+            .collect();
+        if attachments.is_empty() {
+            return;
+        }
+
+        self.expire_pending_images(message_time);
+        debug!(
+            "Holding {} image(s) from untriggered message in chat {}",
+            attachments.len(),
+            chat_guid
+        );
+        let pending = self.pending_images.entry(chat_guid.to_string()).or_default();
+        pending.extend(attachments.into_iter().map(|a| (message_time, a)));
+    }
+
+    /// Drops held images too old to go with a trigger at `now`, so chats
+    /// where nobody wakes the bot don't pile them up.
+    fn expire_pending_images(&mut self, now: u64) {
+        self.pending_images.retain(|_, pending| {
+            pending.retain(|(time, _)| now.saturating_sub(*time) <= PENDING_IMAGE_WINDOW_MS);
+            !pending.is_empty()
+        });
+    }
+
+    /// Returns the trigger's own images followed by recent images posted just before it.
+    fn take_pending_images(
+        &mut self,
+        chat_guid: &str,
+        message_time: u64,
+        mut attachments: Vec<BlueBubblesAttachment>,
+    ) -> Vec<BlueBubblesAttachment> {
+        if let Some(pending) = self.pending_images.remove(chat_guid) {
+            attachments.extend(
+                pending
+                    .into_iter()
+                    .rev()
+                    .filter(|(time, _)| message_time.saturating_sub(*time) <= PENDING_IMAGE_WINDOW_MS)
+                    .map(|(_, attachment)| attachment),
+            );
+        }
+        attachments
+    }
+
     async fn process_message_queue(&mut self) -> Result<()> {
         // Process up to 3 messages from the queue in this tick
         for _ in 0..3 {
-            if let Some((queue_id, chat_guid, message_text)) =
+            if let Some((queue_id, queued_message)) =
                 self.database.get_next_queued_message().await?
             {
+                let chat_guid = queued_message.chat_guid.clone();
                 debug!(
                     "Processing queued message {} for chat {}: {}",
-                    queue_id, chat_guid, message_text
+                    queue_id, chat_guid, queued_message.text
                 );
 
+                // Mentions only count against the limits once the classifier
+                // says they're meant for the bot, when the agent queues them again
+                if !queued_message.mention && !self.within_limits(&queued_message).await? {
+                    self.database.mark_queue_item_completed(queue_id).await?;
+                    metrics().record_message("throttled");
+                    continue;
+                }
+
                 // Ensure chat agent exists
                 if let Err(e) = self.ensure_chat_agent(&chat_guid).await {
                     error!("Failed to create chat agent for {}: {}", chat_guid, e);
                     self.database.mark_queue_item_failed(queue_id).await?;
+                    metrics().record_message("failed");
                     continue;
                 }
 
                 // Send message to chat agent
-                let queued_message = QueuedMessage::new(chat_guid.clone(), message_text);
-
                 if let Some(agent_handle) = self.chat_agents.get(&chat_guid) {
                     match agent_handle.send_message(queued_message).await {
                         Ok(_) => {
                             debug!("Successfully sent queued message {} to agent", queue_id);
                             self.database.mark_queue_item_completed(queue_id).await?;
+                            metrics().record_message("delivered");
                         }
                         Err(e) => {
                             error!("Failed to send queued message {} to agent: {}", queue_id, e);
                             self.database.mark_queue_item_failed(queue_id).await?;
+                            metrics().record_message("failed");
                             // Remove the failed agent so it can be recreated
                             self.remove_chat_agent(&chat_guid).await;
                         }
@@ -210,6 +715,7 @@ impl BotOrchestrator {
                         chat_guid
                     );
                     self.database.mark_queue_item_failed(queue_id).await?;
+                    metrics().record_message("failed");
                 }
             } else {
                 // No more messages in queue
@@ -217,93 +723,170 @@ impl BotOrchestrator {
             }
         }
 
+        metrics().queue_depth.set(self.database.count_queued_messages().await?);
         Ok(())
     }
 
-    async fn check_message_triggers(&self, chat_guid: &str, text: &str) -> Result<bool> {
-        let lower_text = text.to_lowercase();
+    /// Applies the rate limits and daily quotas before a message reaches its
+    /// agent. Returns false if the message should be dropped, letting the
+    /// sender know every few minutes why the bot went quiet.
+    async fn within_limits(&mut self, message: &QueuedMessage) -> Result<bool> {
+        let chat_guid = message.chat_guid.as_str();
+        let sender = message.sender.as_deref();
+
+        let throttle = match self.rate_limiter.check(chat_guid, sender) {
+            Err(throttle) => Some(throttle),
+            // Commands don't cost much, so they still work once the budget's gone
+            Ok(()) if self.command_parser.parse_command(&message.text).is_none() => {
+                self.check_quotas(chat_guid, sender).await?
+            }
+            Ok(()) => None,
+        };
 
-        // Check for @ commands first (these always trigger)
-        let global_triggers = self.config.triggers();
-        debug!(
-            "Checking message '{}' against global triggers: {:?}",
-            lower_text, global_triggers
-        );
+        let Some(throttle) = throttle else {
+            return Ok(true);
+        };
 
-        for trigger in &global_triggers {
-            if lower_text.contains(&trigger.to_lowercase()) {
-                debug!("Found global trigger: {}", trigger);
-                return Ok(true);
+        info!("Dropping message in chat {} from {:?}: {:?} limit", chat_guid, sender, throttle);
+        if self.rate_limiter.should_notify(throttle, chat_guid, sender) {
+            if let Err(e) = self.bluebubbles.send_message(chat_guid, throttle.reply()).await {
+                error!("Failed to send throttling notice to chat {}: {}", chat_guid, e);
             }
         }
+        Ok(false)
+    }
 
-        // Check for NLP trigger (chat-specific name)
-        let trigger_name = match self.database.get_chat_config(chat_guid).await {
-            Ok(Some(chat_config)) => {
-                debug!(
-                    "Found chat config for {}: trigger_name = {}",
-                    chat_guid, chat_config.trigger_name
-                );
-                chat_config.trigger_name.to_lowercase()
-            }
-            Ok(None) => {
-                debug!(
-                    "No chat config found for {}, using default trigger 'myai'",
-                    chat_guid
-                );
-                "myai".to_string()
-            }
-            Err(e) => {
-                debug!(
-                    "Failed to get chat config for {}: {}, using default trigger 'myai'",
-                    chat_guid, e
-                );
-                "myai".to_string()
-            }
+    /// Whether the chat's daily quota leaves room for something the bot does
+    /// unasked, like chiming in or a scheduled post. Nobody's waiting on
+    /// those, so they're skipped quietly.
+    async fn chat_within_quota(&self, chat_guid: &str) -> Result<bool> {
+        let Some(throttle) = self.check_quotas(chat_guid, None).await? else {
+            return Ok(true);
         };
+        info!("Skipping unprompted reply in chat {}: {:?} limit", chat_guid, throttle);
+        Ok(false)
+    }
 
-        debug!(
-            "Checking for NLP trigger '{}' in message '{}'",
-            trigger_name, lower_text
-        );
+    /// Checks today's usage against the chat and sender quotas. Days start at
+    /// midnight in the chat's own time zone, as in `@usage today`.
+    async fn check_quotas(&self, chat_guid: &str, sender: Option<&str>) -> Result<Option<Throttle>> {
+        let timezone = self
+            .database
+            .get_chat_config(chat_guid)
+            .await?
+            .and_then(|chat_config| chat_config.timezone);
+        let tz = resolve_timezone(timezone.as_deref(), &self.config.default_timezone);
+        let today = start_of_day(tz, Utc::now(), 0);
+
+        let chat_quota = self.config.chat_daily_quota;
+        if chat_quota.tokens > 0 || chat_quota.spend > 0.0 {
+            let (tokens, spend) = self.database.get_usage_since(Some(chat_guid), None, today).await?;
+            if chat_quota.exceeded(tokens, spend) {
+                return Ok(Some(Throttle::ChatQuota));
+            }
+        }
 
-        // NLP matching: check if the trigger name appears as a word (not substring)
-        if self.contains_trigger_word(&lower_text, &trigger_name) {
-            debug!("Found NLP trigger: {}", trigger_name);
-            return Ok(true);
+        let sender_quota = self.config.sender_daily_quota;
+        if let Some(sender) = sender.filter(|_| sender_quota.tokens > 0 || sender_quota.spend > 0.0) {
+            let (tokens, spend) = self.database.get_usage_since(None, Some(sender), today).await?;
+            if sender_quota.exceeded(tokens, spend) {
+                return Ok(Some(Throttle::SenderQuota));
+            }
         }
 
-        Ok(false)
+        Ok(None)
     }
 
-    fn contains_trigger_word(&self, text: &str, trigger: &str) -> bool {
-        // Convert both to lowercase for case-insensitive matching
-        let text_lower = text.to_lowercase();
-        let trigger_lower = trigger.to_lowercase();
+    async fn check_message_triggers(
+        &mut self,
+        chat_config: &ChatConfig,
+        text: &str,
+        sender: Option<&str>,
+        message_time: u64,
+        reply_to: Option<&str>,
+    ) -> Result<Addressed> {
+        let chat_guid = chat_config.chat_guid.as_str();
+
+        // Commands wake the bot, but only at the start of a message, so "@help"
+        // doesn't fire on "ask @helpful"
+        if self.command_parser.parse_command(text).is_some() {
+            return Ok(Addressed::Yes);
+        }
 
-        // Simple word boundary matching
-        // Split text into words and check for exact match
-        let words: Vec<&str> = text_lower
-            .split_whitespace()
-            .map(|w| w.trim_matches(|c: char| !c.is_alphanumeric()))
-            .collect();
+        let triggers = self.trigger_set(chat_config);
+        let found = triggers.matches(text);
+        let direct = found && triggers.is_direct(text);
+        debug!("Checked message '{}' against triggers for {}: {}", text, chat_guid, found);
+        if found && (direct || self.config.intent_classifier == IntentClassifier::Off) {
+            return Ok(Addressed::Yes);
+        }
 
-        debug!(
-            "Word analysis: text='{}' -> words={:?}, looking for trigger='{}'",
-            text_lower, words, trigger_lower
-        );
+        // Threaded replies to one of the bot's messages, or the same person
+        // carrying on shortly after the bot answered them
+        let replies_to_bot = match reply_to {
+            Some(originator_guid) => self.is_bot_message(originator_guid).await,
+            None => false,
+        };
+        if continues_conversation(
+            replies_to_bot,
+            self.follow_ups.get(chat_guid),
+            sender,
+            message_time,
+            chat_config.follow_up_secs,
+        ) {
+            debug!("Message in {} continues a conversation with the bot", chat_guid);
+            return Ok(Addressed::Yes);
+        }
+
+        // A mid-sentence mention: the classifier decides whether it's meant for the bot
+        Ok(if found { Addressed::Maybe } else { Addressed::No })
+    }
 
-        let found = words.iter().any(|&word| word == trigger_lower);
-        debug!("Trigger word found: {}", found);
+    /// BOT_TRIGGER, the chat's name and its custom triggers, compiled once and
+    /// reused until the chat's settings change.
+    fn trigger_set(&mut self, chat_config: &ChatConfig) -> &TriggerSet {
+        let chat_guid = &chat_config.chat_guid;
+        let stale = self.trigger_sets.get(chat_guid).is_none_or(|cached| {
+            cached.trigger_name != chat_config.trigger_name || cached.triggers != chat_config.triggers
+        });
+        if stale {
+            debug!("Building triggers for chat {}", chat_guid);
+            self.trigger_sets.insert(
+                chat_guid.clone(),
+                CachedTriggers {
+                    trigger_name: chat_config.trigger_name.clone(),
+                    triggers: chat_config.triggers.clone(),
+                    set: TriggerSet::new(&self.global_triggers, chat_config),
+                },
+            );
+        }
+        &self.trigger_sets[chat_guid].set
+    }
 
-        // Also try simple contains as fallback
-        if !found {
-            let contains_fallback = text_lower.contains(&trigger_lower);
-            debug!("Fallback contains check: {}", contains_fallback);
-            return contains_fallback;
+    /// Whether a message was sent by the bot. Lookups are remembered both
+    /// ways, so replies within a thread don't refetch the message each time.
+    async fn is_bot_message(&mut self, message_guid: &str) -> bool {
+        if self.bot_messages.contains(message_guid) {
+            return true;
+        }
+        if self.other_messages.contains(message_guid) {
+            return false;
         }
 
-        found
+        match self.bluebubbles.get_message(message_guid).await {
+            Ok(Some(message)) => {
+                let from_bot = message.is_from_me == Some(true);
+                let known = if from_bot { &mut self.bot_messages } else { &mut self.other_messages };
+                known.insert(message_guid.to_string());
+                from_bot
+            }
+            // Possibly not synced yet, so look again next time
+            Ok(None) => false,
+            Err(e) => {
+                error!("Failed to fetch replied-to message {}: {}", message_guid, e);
+                false
+            }
+        }
     }
 
     async fn ensure_chat_agent(&self, chat_guid: &str) -> Result<()> {
@@ -323,12 +906,12 @@ impl BotOrchestrator {
             let task_handle = tokio::spawn(async move { agent.run().await });
 
             let agent_handle = ChatAgentHandle {
-                chat_guid: chat_guid.to_string(),
                 sender,
                 task_handle,
             };
 
             self.chat_agents.insert(chat_guid.to_string(), agent_handle);
+            metrics().chat_agents.set(self.chat_agents.len() as i64);
             info!("Created new chat agent for chat: {}", chat_guid);
         }
 
@@ -337,6 +920,7 @@ impl BotOrchestrator {
 
     async fn remove_chat_agent(&self, chat_guid: &str) {
         if let Some((_, agent_handle)) = self.chat_agents.remove(chat_guid) {
+            metrics().chat_agents.set(self.chat_agents.len() as i64);
             info!("Removing chat agent for chat: {}", chat_guid);
 
             // Try to shutdown gracefully
@@ -371,6 +955,14 @@ impl BotOrchestrator {
             error!("Failed to cleanup old database messages: {}", e);
         }
 
+        self.rate_limiter.cleanup();
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("Time went backwards")
+            .as_millis() as u64;
+        self.expire_pending_images(now);
+
         // Cleanup old queue items (older than 1 day)
         if let Err(e) = self.database.cleanup_old_queue_items(1).await {
             error!("Failed to cleanup old queue items: {}", e);
@@ -416,3 +1008,62 @@ impl BotOrchestrator {
         Ok(())
     }
 }
+
+/// Whether a message carries on a conversation with the bot without
+/// needing a trigger: a threaded reply to the bot, or the person it last
+/// answered writing again within the chat's follow-up window.
+fn continues_conversation(
+    replies_to_bot: bool,
+    follow_up: Option<&FollowUp>,
+    sender: Option<&str>,
+    message_time: u64,
+    follow_up_secs: u64,
+) -> bool {
+    if replies_to_bot {
+        return true;
+    }
+    if follow_up_secs == 0 {
+        return false;
+    }
+
+    match (sender, follow_up) {
+        (Some(sender), Some(follow_up)) => {
+            follow_up.sender == sender
+                && message_time.saturating_sub(follow_up.last_activity) <= follow_up_secs * 1000
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn follow_up(sender: &str, last_activity: u64) -> FollowUp {
+        FollowUp {
+            sender: sender.to_string(),
+            last_activity,
+        }
+    }
+
+    #[test]
+    fn test_replies_to_the_bot_continue_the_conversation() {
+        assert!(continues_conversation(true, None, None, 0, 0));
+        assert!(continues_conversation(true, None, Some("alice"), 10_000, 0));
+        assert!(!continues_conversation(false, None, Some("alice"), 10_000, 60));
+    }
+
+    #[test]
+    fn test_follow_ups_within_the_window() {
+        let last = follow_up("alice", 100_000);
+
+        assert!(continues_conversation(false, Some(&last), Some("alice"), 130_000, 60));
+        assert!(continues_conversation(false, Some(&last), Some("alice"), 160_000, 60));
+        assert!(!continues_conversation(false, Some(&last), Some("alice"), 160_001, 60));
+        assert!(!continues_conversation(false, Some(&last), Some("bob"), 130_000, 60));
+        assert!(!continues_conversation(false, Some(&last), None, 130_000, 60));
+
+        // Follow-ups turned off
+        assert!(!continues_conversation(false, Some(&last), Some("alice"), 100_001, 0));
+    }
+}
@@ -0,0 +1,293 @@
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use regex::Regex;
+
+use crate::database::Database;
+use crate::types::{BlueBubblesMessage, Poll};
+
+/// Most options a poll can have: one per tapback.
+pub const MAX_POLL_OPTIONS: usize = 6;
+
+/// How long after a poll goes up a plain "2" counts as a vote without being
+/// a reply to the poll.
+const VOTE_WINDOW_MINS: i64 = 15;
+
+/// Tapbacks in the order they vote for options 1-6.
+const TAPBACKS: [(&str, &str); MAX_POLL_OPTIONS] = [
+    ("love", "❤️"),
+    ("like", "👍"),
+    ("dislike", "👎"),
+    ("laugh", "😂"),
+    ("emphasize", "‼️"),
+    ("question", "❓"),
+];
+
+/// A tapback on a message, as far as polls care: which message it was on,
+/// which option it stands for and whether it was taken back.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tapback {
+    pub target_guid: String,
+    pub option: usize,
+    pub removed: bool,
+}
+
+impl Tapback {
+    /// Reads a tapback from a message. BlueBubbles reports the kind either by
+    /// name ("love", "-love" when removed) or as the raw iMessage code
+    /// (2000-2005, 3000-3005 when removed).
+    pub fn from_message(message: &BlueBubblesMessage) -> Option<Self> {
+        let target = message.associated_message_guid.as_deref()?;
+        // "p:0/<guid>" for a part of a message, "bp:<guid>" for the whole bubble
+        let target_guid = target.rsplit(['/', ':']).next()?.to_string();
+
+        let (kind, removed) = match message.associated_message_type.as_ref()? {
+            serde_json::Value::String(kind) => match kind.strip_prefix('-') {
+                Some(kind) => (kind.to_lowercase(), true),
+                None => (kind.to_lowercase(), false),
+            },
+            serde_json::Value::Number(code) => {
+                let code = code.as_u64()?;
+                let index = (code % 1000) as usize;
+                let removed = code / 1000 == 3;
+                if !(2..=3).contains(&(code / 1000)) || index >= MAX_POLL_OPTIONS {
+                    return None;
+                }
+                (TAPBACKS[index].0.to_string(), removed)
+            }
+            _ => return None,
+        };
+
+        let option = TAPBACKS.iter().position(|(name, _)| *name == kind)?;
+        Some(Self {
+            target_guid,
+            option,
+            removed,
+        })
+    }
+}
+
+/// Reads `"question" option | option | option`, with or without quotes
+/// around the question ("Where to eat? tacos | sushi" works too).
+pub fn parse_poll_spec(text: &str) -> Option<(String, Vec<String>)> {
+    let text = text.trim();
+    let (question, options) = match text.strip_prefix('"').or_else(|| text.strip_prefix('“')) {
+        Some(rest) => rest.split_once(['"', '”'])?,
+        None => {
+            let (question, options) = text.split_once('?')?;
+            return Some((format!("{}?", question.trim()), split_options(options)));
+        }
+    };
+
+    Some((question.trim().to_string(), split_options(options)))
+}
+
+fn split_options(text: &str) -> Vec<String> {
+    text.split('|')
+        .map(|option| option.trim().to_string())
+        .filter(|option| !option.is_empty())
+        .collect()
+}
+
+/// Checks a poll before it's created, returning a message for the chat if
+/// it doesn't work.
+pub fn validate_poll(question: &str, options: &[String]) -> Result<(), String> {
+    if question.trim().is_empty() {
+        return Err("Polls need a question".to_string());
+    }
+    if options.len() < 2 || options.len() > MAX_POLL_OPTIONS {
+        return Err(format!(
+            "Polls need 2-{} options separated by |",
+            MAX_POLL_OPTIONS
+        ));
+    }
+    Ok(())
+}
+
+/// The poll as posted to the chat. The "📊 Poll #id" header is how the bot
+/// recognizes its own poll message when it shows up in the chat.
+pub fn format_poll(poll: &Poll) -> String {
+    let mut lines = vec![format!("📊 Poll #{}: {}", poll.id, poll.question)];
+    lines.extend(
+        poll.options
+            .iter()
+            .enumerate()
+            .map(|(index, option)| format!("{}. {}", index + 1, option)),
+    );
+
+    let tapbacks: Vec<String> = TAPBACKS
+        .iter()
+        .take(poll.options.len())
+        .enumerate()
+        .map(|(index, (_, emoji))| format!("{}={}", emoji, index + 1))
+        .collect();
+    lines.push(format!(
+        "Vote by replying with a number, or tap back {}",
+        tapbacks.join(" ")
+    ));
+    lines.join("\n")
+}
+
+/// Finds the poll id in one of the bot's poll messages.
+pub fn poll_id_from_text(text: &str) -> Option<i64> {
+    let pattern = Regex::new(r"^📊 Poll #(\d+):").expect("poll header pattern compiles");
+    pattern.captures(text.trim())?[1].parse().ok()
+}
+
+/// Reads a vote from a chat message: the option's number ("2", "#2") or its
+/// exact text ("sushi"). Anything else is ordinary chat.
+pub fn parse_vote(text: &str, options: &[String]) -> Option<usize> {
+    let text = text
+        .trim()
+        .trim_end_matches(['.', '!'])
+        .trim_start_matches('#')
+        .trim();
+
+    if let Ok(number) = text.parse::<usize>() {
+        return (1..=options.len()).contains(&number).then(|| number - 1);
+    }
+
+    options
+        .iter()
+        .position(|option| option.eq_ignore_ascii_case(text))
+}
+
+/// Whether a plain-text message can be a vote in the poll: a threaded
+/// reply to the poll message, or a message soon after the poll went up.
+pub fn counts_as_vote(poll: &Poll, reply_to: Option<&str>, sent_at: DateTime<Utc>) -> bool {
+    match reply_to {
+        Some(guid) => poll.message_guid.as_deref() == Some(guid),
+        None => sent_at - poll.created_at <= Duration::minutes(VOTE_WINDOW_MINS),
+    }
+}
+
+/// Announces how a poll went. `votes` holds the vote count per option.
+pub fn format_results(poll: &Poll, votes: &[i64]) -> String {
+    let total: i64 = votes.iter().sum();
+    if total == 0 {
+        return format!("📊 Poll #{} closed: {}\nNobody voted.", poll.id, poll.question);
+    }
+
+    let top = votes.iter().copied().max().unwrap_or(0);
+    let mut lines = vec![format!("📊 Poll #{} results: {}", poll.id, poll.question)];
+    lines.extend(poll.options.iter().enumerate().map(|(index, option)| {
+        let count = votes.get(index).copied().unwrap_or(0);
+        format!(
+            "{}. {} — {} vote{}{}",
+            index + 1,
+            option,
+            count,
+            if count == 1 { "" } else { "s" },
+            if count == top { " 🏆" } else { "" }
+        )
+    }));
+    lines.join("\n")
+}
+
+/// Closes a poll and returns the results to announce, or None if it had
+/// already been closed.
+pub async fn close_poll(database: &Database, poll: &Poll) -> Result<Option<String>> {
+    if !database.close_poll(poll.id).await? {
+        return Ok(None);
+    }
+    let votes = database.get_poll_votes(poll).await?;
+    Ok(Some(format_results(poll, &votes)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn poll(options: &[&str]) -> Poll {
+        Poll {
+            id: 7,
+            chat_guid: "chat".to_string(),
+            question: "Where should we eat?".to_string(),
+            options: options.iter().map(|o| o.to_string()).collect(),
+            message_guid: Some("poll-message".to_string()),
+            created_at: DateTime::from_timestamp(1_700_000_000, 0).unwrap(),
+        }
+    }
+
+    fn tapback_message(target: &str, kind: serde_json::Value) -> BlueBubblesMessage {
+        serde_json::from_value(serde_json::json!({
+            "guid": "tapback",
+            "associatedMessageGuid": target,
+            "associatedMessageType": kind,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_parse_poll_spec() {
+        let (question, options) = parse_poll_spec(r#""Where should we eat?" tacos | sushi | pizza"#).unwrap();
+        assert_eq!(question, "Where should we eat?");
+        assert_eq!(options, vec!["tacos", "sushi", "pizza"]);
+
+        let (question, options) = parse_poll_spec("Friday or Saturday? fri | sat").unwrap();
+        assert_eq!(question, "Friday or Saturday?");
+        assert_eq!(options, vec!["fri", "sat"]);
+
+        assert!(parse_poll_spec("no question here").is_none());
+        assert!(validate_poll("Which?", &["one".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_format_poll_round_trips_id() {
+        let text = format_poll(&poll(&["Tacos", "Sushi"]));
+        assert!(text.starts_with("📊 Poll #7: Where should we eat?\n1. Tacos\n2. Sushi"));
+        assert!(text.ends_with("❤️=1 👍=2"));
+        assert_eq!(poll_id_from_text(&text), Some(7));
+        assert_eq!(poll_id_from_text("Poll #7: nope"), None);
+    }
+
+    #[test]
+    fn test_parse_vote() {
+        let options = vec!["Tacos".to_string(), "Sushi".to_string()];
+
+        assert_eq!(parse_vote("2", &options), Some(1));
+        assert_eq!(parse_vote(" #1 ", &options), Some(0));
+        assert_eq!(parse_vote("sushi!", &options), Some(1));
+        assert_eq!(parse_vote("3", &options), None);
+        assert_eq!(parse_vote("I'd say sushi", &options), None);
+    }
+
+    #[test]
+    fn test_votes_need_a_reply_or_to_be_recent() {
+        let poll = poll(&["Tacos", "Sushi"]);
+        let posted = poll.created_at;
+
+        assert!(counts_as_vote(&poll, None, posted + Duration::minutes(5)));
+        assert!(!counts_as_vote(&poll, None, posted + Duration::hours(2)));
+        assert!(counts_as_vote(&poll, Some("poll-message"), posted + Duration::hours(2)));
+        assert!(!counts_as_vote(&poll, Some("other-message"), posted + Duration::minutes(5)));
+    }
+
+    #[test]
+    fn test_tapbacks_map_to_options() {
+        let like = tapback_message("p:0/ABC-123", serde_json::json!("like"));
+        assert_eq!(
+            Tapback::from_message(&like),
+            Some(Tapback { target_guid: "ABC-123".to_string(), option: 1, removed: false })
+        );
+
+        let removed = tapback_message("bp:ABC-123", serde_json::json!(3003));
+        assert_eq!(
+            Tapback::from_message(&removed),
+            Some(Tapback { target_guid: "ABC-123".to_string(), option: 3, removed: true })
+        );
+
+        let not_tapback = tapback_message("p:0/ABC-123", serde_json::json!(1000));
+        assert!(Tapback::from_message(&not_tapback).is_none());
+    }
+
+    #[test]
+    fn test_format_results_marks_winners() {
+        let results = format_results(&poll(&["Tacos", "Sushi", "Pizza"]), &[2, 2, 1]);
+        assert!(results.contains("1. Tacos — 2 votes 🏆"));
+        assert!(results.contains("2. Sushi — 2 votes 🏆"));
+        assert!(results.ends_with("3. Pizza — 1 vote"));
+
+        let results = format_results(&poll(&["Tacos", "Sushi"]), &[0, 0]);
+        assert!(results.ends_with("Nobody voted."));
+    }
+}
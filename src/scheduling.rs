@@ -0,0 +1,258 @@
+use chrono::{DateTime, Duration, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::{Tz, TZ_VARIANTS};
+use cron::Schedule;
+use regex::Regex;
+use std::str::FromStr;
+
+/// Looks up an IANA time zone name, ignoring case ("america/new_york" works).
+pub fn parse_timezone(name: &str) -> Option<Tz> {
+    let name = name.trim();
+    name.parse()
+        .ok()
+        .or_else(|| TZ_VARIANTS.iter().copied().find(|tz| tz.name().eq_ignore_ascii_case(name)))
+}
+
+/// The time zone a chat's times are read and shown in: the chat's own if it
+/// set a valid one, otherwise the global default.
+pub fn resolve_timezone(chat_timezone: Option<&str>, default: &str) -> Tz {
+    chat_timezone
+        .and_then(parse_timezone)
+        .or_else(|| parse_timezone(default))
+        .unwrap_or(Tz::UTC)
+}
+
+/// Reads a wall-clock time like "2026-10-19T09:00" in the given zone. Offsets
+/// aren't expected; the model is told the chat's local time and answers in it.
+pub fn parse_local_datetime(text: &str, tz: Tz) -> Option<DateTime<Utc>> {
+    let text = text.trim();
+
+    // Accept a full RFC 3339 timestamp too, in case the model adds an offset
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(text) {
+        return Some(datetime.with_timezone(&Utc));
+    }
+
+    let naive = ["%Y-%m-%dT%H:%M:%S", "%Y-%m-%dT%H:%M", "%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(text, format).ok())?;
+
+    // Skipped by a DST change: nothing sensible to pick
+    tz.from_local_datetime(&naive)
+        .earliest()
+        .map(|datetime| datetime.with_timezone(&Utc))
+}
+
+/// Formats a time for chat messages, e.g. "Mon Oct 19, 9:00 AM".
+pub fn format_local(datetime: DateTime<Utc>, tz: Tz) -> String {
+    datetime.with_timezone(&tz).format("%a %b %-d, %-I:%M %p").to_string()
+}
+
+/// The current local time, for telling the model what "tomorrow" means.
+pub fn describe_now(tz: Tz) -> String {
+    format!(
+        "{} ({})",
+        Utc::now().with_timezone(&tz).format("%A %Y-%m-%d %H:%M"),
+        tz.name()
+    )
+}
+
+/// Midnight at the start of `days_back` days before today in the zone, for
+/// "today" (0) and "this week" (6) totals. Falls back to 24 hours a day if
+/// midnight doesn't exist there.
+pub fn start_of_day(tz: Tz, now: DateTime<Utc>, days_back: i64) -> DateTime<Utc> {
+    let date = now.with_timezone(&tz).date_naive() - Duration::days(days_back);
+    date.and_hms_opt(0, 0, 0)
+        .and_then(|midnight| tz.from_local_datetime(&midnight).earliest())
+        .map(|midnight| midnight.with_timezone(&Utc))
+        .unwrap_or_else(|| now - Duration::days(days_back + 1))
+}
+
+/// Turns a recurring schedule into a cron expression (with seconds, as the
+/// `cron` crate expects). Understands plain English like "every weekday 8am",
+/// "sunday at 6pm", "mon, wed 7:30pm" or "daily noon", and raw 5-field cron
+/// after a `cron` prefix ("cron 0 8 * * Mon-Fri").
+pub fn parse_recurrence(text: &str) -> Result<String, String> {
+    let text = text.trim();
+
+    let cron_expression = text
+        .get(..5)
+        .filter(|prefix| prefix.eq_ignore_ascii_case("cron "))
+        .map(|_| &text[5..]);
+    if let Some(expression) = cron_expression {
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        if fields.len() != 5 {
+            return Err("Cron schedules need 5 fields: minute hour day month weekday".to_string());
+        }
+        let expression = format!("0 {}", fields.join(" "));
+        Schedule::from_str(&expression).map_err(|e| format!("That cron schedule doesn't parse: {}", e))?;
+        return Ok(expression);
+    }
+
+    let text = text.to_lowercase();
+    let pattern = Regex::new(
+        r"^(?:every\s+)?(?:(.+?)\s+)?(?:at\s+)?(noon|midnight|\d{1,2}(?::\d{2})?\s*(?:am|pm)?)$",
+    )
+    .expect("recurrence pattern compiles");
+    let captures = pattern
+        .captures(&text)
+        .ok_or_else(|| "Try something like \"every weekday 8am\" or \"sunday 6pm\"".to_string())?;
+
+    let (hour, minute) = parse_time_of_day(&captures[2])
+        .ok_or_else(|| format!("'{}' isn't a time of day", &captures[2]))?;
+    let days = match captures.get(1) {
+        Some(days) => parse_days(days.as_str())?,
+        None => "*".to_string(),
+    };
+
+    Ok(format!("0 {} {} * * {}", minute, hour, days))
+}
+
+/// The next time a cron expression fires after `after`, read in the chat's
+/// time zone so "8am" means 8am there.
+pub fn next_run(expression: &str, tz: Tz, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    let schedule = Schedule::from_str(expression).ok()?;
+    schedule
+        .after(&after.with_timezone(&tz))
+        .next()
+        .map(|datetime| datetime.with_timezone(&Utc))
+}
+
+fn parse_time_of_day(text: &str) -> Option<(u32, u32)> {
+    match text {
+        "noon" => return Some((12, 0)),
+        "midnight" => return Some((0, 0)),
+        _ => {}
+    }
+
+    let (clock, suffix) = match text.trim_end_matches("am").trim_end_matches("pm") {
+        clock if clock.len() < text.len() => (clock.trim(), Some(&text[clock.len()..])),
+        clock => (clock, None),
+    };
+    let (hour, minute) = match clock.split_once(':') {
+        Some((hour, minute)) => (hour.parse::<u32>().ok()?, minute.parse::<u32>().ok()?),
+        None => (clock.parse::<u32>().ok()?, 0),
+    };
+
+    let hour = match suffix {
+        Some(_) if !(1..=12).contains(&hour) => return None,
+        Some("am") => hour % 12,
+        Some(_) => hour % 12 + 12,
+        None => hour,
+    };
+    (hour < 24 && minute < 60).then_some((hour, minute))
+}
+
+fn parse_days(text: &str) -> Result<String, String> {
+    match text.trim() {
+        "day" | "days" | "daily" => return Ok("*".to_string()),
+        "weekday" | "weekdays" => return Ok("Mon-Fri".to_string()),
+        "weekend" | "weekends" => return Ok("Sat,Sun".to_string()),
+        _ => {}
+    }
+
+    // Each day's cron name and the ways people write it
+    const DAYS: [(&str, &[&str]); 7] = [
+        ("Mon", &["mon", "monday"]),
+        ("Tue", &["tue", "tues", "tuesday"]),
+        ("Wed", &["wed", "weds", "wednesday"]),
+        ("Thu", &["thu", "thur", "thurs", "thursday"]),
+        ("Fri", &["fri", "friday"]),
+        ("Sat", &["sat", "saturday"]),
+        ("Sun", &["sun", "sunday"]),
+    ];
+    let mut days = Vec::new();
+    for word in text.split(|c: char| c == ',' || c.is_whitespace()) {
+        if word.is_empty() || word == "and" {
+            continue;
+        }
+        // "mondays" means the same as "monday"
+        let singular = word.strip_suffix('s').unwrap_or(word);
+        let (day, _) = DAYS
+            .iter()
+            .find(|(_, names)| names.contains(&word) || names.contains(&singular))
+            .ok_or_else(|| format!("'{}' isn't a day of the week", word))?;
+        if !days.contains(day) {
+            days.push(*day);
+        }
+    }
+
+    if days.is_empty() {
+        return Err("Say which days, like weekdays, daily or monday".to_string());
+    }
+    Ok(days.join(","))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_local_datetime_uses_chat_zone() {
+        let tz: Tz = "America/New_York".parse().unwrap();
+
+        let utc = parse_local_datetime("2026-10-19T09:00", tz).unwrap();
+        assert_eq!(utc.to_rfc3339(), "2026-10-19T13:00:00+00:00");
+
+        let utc = parse_local_datetime("2026-10-19 09:00:30", tz).unwrap();
+        assert_eq!(utc.to_rfc3339(), "2026-10-19T13:00:30+00:00");
+
+        let utc = parse_local_datetime("2026-10-19T09:00:00+02:00", tz).unwrap();
+        assert_eq!(utc.to_rfc3339(), "2026-10-19T07:00:00+00:00");
+
+        assert!(parse_local_datetime("tomorrow at 9", tz).is_none());
+        // Lost to the spring-forward gap
+        assert!(parse_local_datetime("2026-03-08T02:30", tz).is_none());
+    }
+
+    #[test]
+    fn test_resolve_timezone_falls_back() {
+        assert_eq!(resolve_timezone(Some("Europe/Paris"), "UTC"), Tz::Europe__Paris);
+        assert_eq!(resolve_timezone(Some("Mars/Base"), "Asia/Tokyo"), Tz::Asia__Tokyo);
+        assert_eq!(resolve_timezone(None, "nonsense"), Tz::UTC);
+        assert_eq!(parse_timezone("america/chicago"), Some(Tz::America__Chicago));
+    }
+
+    #[test]
+    fn test_parse_recurrence() {
+        assert_eq!(parse_recurrence("every weekday 8am").unwrap(), "0 0 8 * * Mon-Fri");
+        assert_eq!(parse_recurrence("Sunday at 6pm").unwrap(), "0 0 18 * * Sun");
+        assert_eq!(parse_recurrence("mon, wed and fridays 7:30pm").unwrap(), "0 30 19 * * Mon,Wed,Fri");
+        assert_eq!(parse_recurrence("daily noon").unwrap(), "0 0 12 * * *");
+        assert_eq!(parse_recurrence("21:15").unwrap(), "0 15 21 * * *");
+        assert_eq!(parse_recurrence("cron 0 8 * * Mon-Fri").unwrap(), "0 0 8 * * Mon-Fri");
+
+        assert_eq!(parse_recurrence("tues and thurs 9am").unwrap(), "0 0 9 * * Tue,Thu");
+
+        assert!(parse_recurrence("every blursday 8am").is_err());
+        assert!(parse_recurrence("every month 8am").is_err());
+        assert!(parse_recurrence("sunny days 8am").is_err());
+        assert!(parse_recurrence("every day 13pm").is_err());
+        assert!(parse_recurrence("sometimes").is_err());
+        assert!(parse_recurrence("cron 0 8 * *").is_err());
+    }
+
+    #[test]
+    fn test_next_run_in_chat_zone() {
+        let tz: Tz = "America/New_York".parse().unwrap();
+        // Friday evening in New York
+        let after = parse_local_datetime("2026-10-16T19:00", tz).unwrap();
+
+        let next = next_run("0 0 8 * * Mon-Fri", tz, after).unwrap();
+        assert_eq!(format_local(next, tz), "Mon Oct 19, 8:00 AM");
+    }
+
+    #[test]
+    fn test_start_of_day() {
+        let tz: Tz = "America/New_York".parse().unwrap();
+        let now = parse_local_datetime("2026-10-19T15:30", tz).unwrap();
+
+        assert_eq!(format_local(start_of_day(tz, now, 0), tz), "Mon Oct 19, 12:00 AM");
+        assert_eq!(format_local(start_of_day(tz, now, 6), tz), "Tue Oct 13, 12:00 AM");
+    }
+
+    #[test]
+    fn test_format_local() {
+        let tz: Tz = "America/New_York".parse().unwrap();
+        let utc = parse_local_datetime("2026-10-19T21:05", tz).unwrap();
+        assert_eq!(format_local(utc, tz), "Mon Oct 19, 9:05 PM");
+    }
+}
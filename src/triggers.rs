@@ -0,0 +1,224 @@
+use regex::{Regex, RegexBuilder};
+
+use crate::types::ChatConfig;
+
+/// Most custom triggers a single chat can have.
+pub const MAX_CHAT_TRIGGERS: usize = 10;
+
+/// A single thing that wakes the bot up.
+///
+/// Triggers are stored as plain strings: `/pattern/` is a case-insensitive
+/// regex, text made of letters, numbers and spaces is a word or phrase that
+/// has to appear on its own (so "myai" doesn't match "myairplane"), and
+/// anything else (emoji, "@ava") matches wherever it appears, as long as a
+/// trailing name isn't the start of a longer word ("@ava" doesn't match
+/// "@avalanche").
+#[derive(Debug, Clone)]
+pub enum Trigger {
+    Phrase(Regex),
+    Literal(Regex),
+    Pattern(Regex),
+}
+
+impl Trigger {
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let spec = spec.trim();
+        if spec.is_empty() {
+            return Err("Triggers can't be empty".to_string());
+        }
+        if spec.chars().count() > 100 {
+            return Err("Triggers can be at most 100 characters".to_string());
+        }
+
+        if let Some(pattern) = spec
+            .strip_prefix('/')
+            .and_then(|rest| rest.strip_suffix('/'))
+            .filter(|pattern| !pattern.is_empty())
+        {
+            let regex = build_regex(pattern)
+                .map_err(|e| format!("That regex doesn't compile: {}", e))?;
+            return Ok(Trigger::Pattern(regex));
+        }
+
+        if spec.chars().all(|c| c.is_alphanumeric() || c.is_whitespace()) {
+            let words: Vec<String> = spec.split_whitespace().map(regex::escape).collect();
+            if words.concat().chars().count() < 2 {
+                return Err("Word triggers need at least 2 characters".to_string());
+            }
+            // Words must stand alone, separated by anything that isn't a letter or digit
+            let pattern = format!(r"\b{}\b", words.join(r"[^\p{Alphabetic}\p{N}]+"));
+            return Ok(Trigger::Phrase(build_regex(&pattern).map_err(|e| e.to_string())?));
+        }
+
+        let mut pattern = regex::escape(spec);
+        if spec.ends_with(|c: char| c.is_alphanumeric() || c == '_') {
+            pattern.push_str(r"\b");
+        }
+        let regex = build_regex(&pattern).map_err(|e| e.to_string())?;
+        Ok(Trigger::Literal(regex))
+    }
+
+    fn regex(&self) -> &Regex {
+        match self {
+            Trigger::Phrase(regex) | Trigger::Literal(regex) | Trigger::Pattern(regex) => regex,
+        }
+    }
+}
+
+/// Everything that triggers the bot in one chat: the global triggers, the
+/// chat's name and its custom triggers.
+#[derive(Debug, Clone)]
+pub struct TriggerSet {
+    triggers: Vec<Trigger>,
+}
+
+impl TriggerSet {
+    pub fn new(global_triggers: &[String], chat_config: &ChatConfig) -> Self {
+        let triggers = global_triggers
+            .iter()
+            .chain(std::iter::once(&chat_config.trigger_name))
+            .chain(&chat_config.triggers)
+            .filter_map(|spec| Trigger::parse(spec).ok())
+            .collect();
+
+        Self { triggers }
+    }
+
+    pub fn matches(&self, text: &str) -> bool {
+        self.triggers
+            .iter()
+            .any(|trigger| trigger.regex().is_match(text))
+    }
+
+    /// Whether a trigger opens or closes the message ("myai, ..." or
+    /// "... right myai?"), which reads as talking to the bot rather than
+    /// about it.
+    pub fn is_direct(&self, text: &str) -> bool {
+        let is_filler = |s: &str| !s.chars().any(|c| c.is_alphanumeric());
+
+        self.triggers.iter().any(|trigger| {
+            trigger
+                .regex()
+                .find_iter(text)
+                .any(|m| is_filler(&text[..m.start()]) || is_filler(&text[m.end()..]))
+        })
+    }
+
+    /// Removes trigger tokens so the model only sees the actual request:
+    /// "myai, what's the weather?" becomes "what's the weather?". Returns the
+    /// text unchanged if nothing would be left.
+    pub fn strip(&self, text: &str) -> String {
+        let mut stripped = text.to_string();
+        for trigger in &self.triggers {
+            stripped = trigger.regex().replace_all(&stripped, " ").into_owned();
+        }
+
+        // Tidy the gaps left behind, keeping line breaks
+        let stripped = stripped
+            .lines()
+            .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n");
+        let stripped = stripped
+            .trim_start_matches(|c: char| c.is_whitespace() || matches!(c, ',' | ':' | ';' | '-' | '—'))
+            .trim();
+
+        if stripped.is_empty() {
+            text.trim().to_string()
+        } else {
+            stripped.to_string()
+        }
+    }
+}
+
+/// Drops global triggers that older versions copied into every chat's
+/// trigger list, leaving only the chat's own.
+pub fn custom_triggers(chat_triggers: &[String], global_triggers: &[String]) -> Vec<String> {
+    chat_triggers
+        .iter()
+        .filter(|trigger| !global_triggers.contains(trigger))
+        .cloned()
+        .collect()
+}
+
+fn build_regex(pattern: &str) -> Result<Regex, regex::Error> {
+    RegexBuilder::new(pattern).case_insensitive(true).build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trigger_set(triggers: &[&str]) -> TriggerSet {
+        let mut config = ChatConfig::new("chat".to_string());
+        config.triggers = triggers.iter().map(|t| t.to_string()).collect();
+        TriggerSet::new(&["@ava".to_string()], &config)
+    }
+
+    #[test]
+    fn test_words_match_whole_words_only() {
+        let triggers = trigger_set(&[]);
+
+        assert!(triggers.matches("hey MyAI, what's up?"));
+        assert!(triggers.matches("myai"));
+        assert!(!triggers.matches("my airplane is late"));
+        assert!(!triggers.matches("myairplane"));
+    }
+
+    #[test]
+    fn test_aliases_emoji_and_regex() {
+        let triggers = trigger_set(&["hey bot", "🤖", "/^yo\\b/"]);
+
+        assert!(triggers.matches("well hey   bot!"));
+        assert!(!triggers.matches("hey bottle"));
+        assert!(triggers.matches("can you help 🤖"));
+        assert!(triggers.matches("Yo what's good"));
+        assert!(!triggers.matches("I said yo"));
+        assert!(triggers.matches("ping @ava"));
+        assert!(triggers.matches("@ava, hi"));
+        assert!(!triggers.matches("ping @avalanche"));
+    }
+
+    #[test]
+    fn test_direct_mentions() {
+        let triggers = trigger_set(&[]);
+
+        assert!(triggers.is_direct("myai, what's up?"));
+        assert!(triggers.is_direct("what do you think, MyAI?"));
+        assert!(!triggers.is_direct("I told myai yesterday about it"));
+        assert!(!triggers.is_direct("no trigger here"));
+    }
+
+    #[test]
+    fn test_strip_removes_trigger_tokens() {
+        let triggers = trigger_set(&["hey bot", "🤖"]);
+
+        assert_eq!(triggers.strip("MyAI, what's the weather?"), "what's the weather?");
+        assert_eq!(triggers.strip("what do you think myai"), "what do you think");
+        assert_eq!(triggers.strip("hey bot 🤖 tell me a joke"), "tell me a joke");
+        assert_eq!(triggers.strip("@ava summarize this"), "summarize this");
+        assert_eq!(triggers.strip("my airplane is late"), "my airplane is late");
+
+        assert_eq!(triggers.strip("myai\nfirst line\nsecond  line"), "first line\nsecond line");
+
+        // Nothing left: keep the original
+        assert_eq!(triggers.strip("myai"), "myai");
+    }
+
+    #[test]
+    fn test_parse_rejects_bad_triggers() {
+        assert!(Trigger::parse("   ").is_err());
+        assert!(Trigger::parse("a").is_err());
+        assert!(Trigger::parse("/(unclosed/").is_err());
+        assert!(matches!(Trigger::parse("//"), Ok(Trigger::Literal(_))));
+    }
+
+    #[test]
+    fn test_custom_triggers_drop_legacy_globals() {
+        let global = vec!["@ava".to_string(), "@character".to_string()];
+        let chat = vec!["@ava".to_string(), "@character".to_string(), "bestie".to_string()];
+
+        assert_eq!(custom_triggers(&chat, &global), vec!["bestie".to_string()]);
+    }
+}
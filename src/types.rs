@@ -1,6 +1,5 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::VecDeque;
 use uuid::Uuid;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -8,6 +7,8 @@ pub struct Message {
     pub role: MessageRole,
     pub content: String,
     pub timestamp: DateTime<Utc>,
+    #[serde(default)]
+    pub attachments: Vec<BlueBubblesAttachment>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -28,6 +29,26 @@ pub struct BlueBubblesMessage {
     #[serde(rename = "isFromMe")]
     pub is_from_me: Option<bool>,
     pub attachments: Option<Vec<BlueBubblesAttachment>>,
+    #[serde(rename = "threadOriginatorGuid")]
+    pub thread_originator_guid: Option<String>,
+    pub handle: Option<BlueBubblesHandle>,
+    // Set on tapbacks: the message reacted to and the kind of reaction
+    #[serde(rename = "associatedMessageGuid")]
+    pub associated_message_guid: Option<String>,
+    #[serde(rename = "associatedMessageType")]
+    pub associated_message_type: Option<serde_json::Value>,
+}
+
+impl BlueBubblesMessage {
+    /// Address (phone number or email) of whoever sent the message.
+    pub fn sender(&self) -> Option<&str> {
+        self.handle.as_ref().map(|handle| handle.address.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlueBubblesHandle {
+    pub address: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -56,81 +77,222 @@ pub struct BlueBubblesChat {
 pub struct ChatConfig {
     pub chat_guid: String,
     pub character_prompt: Option<String>,
-    pub triggers: Vec<String>,
+    pub triggers: Vec<String>, // Custom trigger specs, see `triggers::Trigger`
     pub trigger_name: String, // NLP trigger name like "myai", "bot", "assistant"
     pub use_ollama: bool,
+    pub reply_mode: ReplyMode,
+    pub voice: Option<String>, // TTS voice, None uses the global default
+    pub openai_model: Option<String>, // OpenAI chat model override, None uses the default
+    pub ollama_model: Option<String>, // Ollama chat model override, None uses OLLAMA_MODEL
+    pub temperature: f32,
+    pub context_size: usize, // Number of recent messages sent to the model
+    pub paused: bool, // Set by @bot off
+    pub follow_up_secs: u64, // Untriggered follow-ups accepted this long after a reply, 0 = off
+    pub intent_threshold: f32, // Classifier confidence needed for mid-sentence mentions
+    pub proactive: bool, // Chime in without being triggered
+    pub timezone: Option<String>, // IANA name, None uses DEFAULT_TIMEZONE
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Clone)]
-pub struct ChatContext {
-    pub chat_guid: String,
-    pub messages: VecDeque<Message>,
-    pub character_prompt: Option<String>,
-    pub use_ollama: bool,
-    pub triggers: Vec<String>,
-}
-
-impl ChatContext {
-    pub fn new(chat_guid: String, config: Option<ChatConfig>) -> Self {
-        let (character_prompt, use_ollama, triggers) = match config {
-            Some(config) => (config.character_prompt, config.use_ollama, config.triggers),
-            None => (None, false, vec![]),
-        };
-
+impl ChatConfig {
+    pub fn new(chat_guid: String) -> Self {
         Self {
             chat_guid,
-            messages: VecDeque::new(),
-            character_prompt,
-            use_ollama,
-            triggers,
+            character_prompt: None,
+            triggers: Vec::new(),
+            trigger_name: "myai".to_string(),
+            use_ollama: false,
+            reply_mode: ReplyMode::Text,
+            voice: None,
+            openai_model: None,
+            ollama_model: None,
+            temperature: 0.7,
+            context_size: 10,
+            paused: false,
+            follow_up_secs: 0,
+            intent_threshold: 0.5,
+            proactive: false,
+            timezone: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
         }
     }
 
-    pub fn add_message(&mut self, message: Message) {
-        self.messages.push_back(message);
-        
-        // Keep only last 10 messages
-        while self.messages.len() > 10 {
-            self.messages.pop_front();
+    /// The model override for the provider the chat is using now. Each
+    /// provider keeps its own, so `@unhinge` never sends an OpenAI model
+    /// name to Ollama.
+    pub fn model(&self) -> Option<&str> {
+        if self.use_ollama {
+            self.ollama_model.as_deref()
+        } else {
+            self.openai_model.as_deref()
         }
     }
 
-    pub fn get_system_prompt(&self) -> String {
-        self.character_prompt.clone().unwrap_or_else(|| {
-            "You are MyAI, a casual assistant in a private friend group chat. Be brief and natural unless asked to elaborate. Match the group's tone and energy.".to_string()
-        })
+    /// Sets the model override for the provider the chat is using now.
+    pub fn set_model(&mut self, model: Option<String>) {
+        if self.use_ollama {
+            self.ollama_model = model;
+        } else {
+            self.openai_model = model;
+        }
     }
 }
 
+/// How the bot delivers its replies in a chat.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReplyMode {
+    Text,
+    Voice,
+    VoiceAndText,
+}
+
+impl ReplyMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReplyMode::Text => "text",
+            ReplyMode::Voice => "voice",
+            ReplyMode::VoiceAndText => "both",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_lowercase().as_str() {
+            "text" | "off" => Some(ReplyMode::Text),
+            "voice" | "on" => Some(ReplyMode::Voice),
+            "both" | "voice+text" => Some(ReplyMode::VoiceAndText),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CachedAttachment {
+    pub attachment_guid: String,
+    pub chat_guid: String,
+    pub content_hash: String,
+    pub mime_type: Option<String>,
+    pub filename: Option<String>,
+    pub size_bytes: i64,
+    pub source: String, // 'downloaded', 'generated'
+    pub description: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A named character prompt saved from a chat. Shared personas can be used
+/// from any chat.
+#[derive(Debug, Clone)]
+pub struct Persona {
+    pub name: String,
+    pub chat_guid: String, // chat that saved it
+    pub prompt: String,
+    pub shared: bool,
+}
+
+/// A message the bot will send to a chat later, e.g. a reminder.
+#[derive(Debug, Clone)]
+pub struct ScheduledMessage {
+    pub id: i64,
+    pub chat_guid: String,
+    pub message: String,
+    pub send_at: DateTime<Utc>,
+    pub attempts: i64, // Failed sends so far
+}
+
+/// A prompt the bot runs on a schedule, posting the model's answer to the
+/// chat ("every weekday 8am: motivational quote in character").
+#[derive(Debug, Clone)]
+pub struct RecurringJob {
+    pub id: i64,
+    pub chat_guid: String,
+    pub schedule: String,    // Cron expression with seconds
+    pub description: String, // The schedule as the user wrote it
+    pub prompt: String,
+    pub next_run_at: DateTime<Utc>,
+    pub last_run_at: Option<DateTime<Utc>>,
+}
+
+/// A question the chat is voting on. Votes live in `poll_votes`.
+#[derive(Debug, Clone)]
+pub struct Poll {
+    pub id: i64,
+    pub chat_guid: String,
+    pub question: String,
+    pub options: Vec<String>,
+    pub message_guid: Option<String>, // The bot's poll message, once it's seen it
+    pub created_at: DateTime<Utc>,
+}
+
+/// One model call: who it was for, what it used and roughly what it cost.
+#[derive(Debug, Clone)]
+pub struct UsageEvent {
+    pub chat_guid: String,
+    pub sender: Option<String>,
+    pub provider: String, // "openai" or "ollama"
+    pub kind: String,     // "chat", "intent", "character" or "image"
+    pub model: String,
+    pub prompt_tokens: i64,
+    pub completion_tokens: i64,
+    pub image_count: i64,
+    pub latency_ms: i64,
+    pub cost_usd: f64,
+}
+
+/// Usage totals for one provider and model over a period.
+#[derive(Debug, Clone)]
+pub struct UsageSummary {
+    pub provider: String,
+    pub model: String,
+    pub requests: i64,
+    pub tokens: i64,
+    pub images: i64,
+    pub cost_usd: f64,
+    pub avg_latency_ms: i64,
+}
+
+/// Usage totals for one sender over a period.
+#[derive(Debug, Clone)]
+pub struct SenderUsage {
+    pub sender: Option<String>,
+    pub tokens: i64,
+    pub cost_usd: f64,
+}
+
+/// An untriggered message the bot overheard, kept for proactive mode.
+#[derive(Debug, Clone)]
+pub struct AmbientMessage {
+    pub sender: Option<String>,
+    pub text: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct QueuedMessage {
     pub id: Uuid,
     pub chat_guid: String,
+    pub sender: Option<String>,
     pub text: String,
+    pub attachments: Vec<BlueBubblesAttachment>,
+    pub mention: bool, // Only mentions the bot mid-sentence: the intent classifier decides whether to answer
     pub timestamp: DateTime<Utc>,
 }
 
 impl QueuedMessage {
-    pub fn new(chat_guid: String, text: String) -> Self {
+    pub fn new(
+        chat_guid: String,
+        sender: Option<String>,
+        text: String,
+        attachments: Vec<BlueBubblesAttachment>,
+        mention: bool,
+    ) -> Self {
         Self {
             id: Uuid::new_v4(),
             chat_guid,
+            sender,
             text,
+            attachments,
+            mention,
             timestamp: Utc::now(),
         }
     }
 }
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct OpenAIMessage {
-    pub role: String,
-    pub content: String,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct OllamaMessage {
-    pub role: String,
-    pub content: String,
-}
\ No newline at end of file
@@ -0,0 +1,168 @@
+use tracing::error;
+
+use crate::ai_clients::{ChatCompletion, OPENAI_IMAGE_MODEL};
+use crate::config::Config;
+use crate::database::Database;
+use crate::limits::ModelPrices;
+use crate::types::{SenderUsage, UsageEvent, UsageSummary};
+
+/// Records what each model call used and cost, for quotas and `@usage`.
+/// Failures are logged rather than returned: losing a usage row shouldn't
+/// lose the reply.
+#[derive(Clone)]
+pub struct UsageRecorder {
+    database: Database,
+    prices: ModelPrices,
+    image_price: f64,
+}
+
+impl UsageRecorder {
+    pub fn from_config(config: &Config, database: Database) -> Self {
+        Self {
+            database,
+            prices: ModelPrices::parse(&config.model_prices),
+            image_price: config.image_price,
+        }
+    }
+
+    /// Records a chat completion. `kind` says what it was for: "chat",
+    /// "intent" or "character".
+    pub async fn record_completion(
+        &self,
+        chat_guid: &str,
+        sender: Option<&str>,
+        kind: &str,
+        completion: &ChatCompletion,
+    ) {
+        let usage = completion.usage.unwrap_or_default();
+        let event = UsageEvent {
+            chat_guid: chat_guid.to_string(),
+            sender: sender.map(str::to_string),
+            provider: completion.provider.to_string(),
+            kind: kind.to_string(),
+            model: completion.model.clone(),
+            prompt_tokens: usage.prompt_tokens,
+            completion_tokens: usage.completion_tokens,
+            image_count: 0,
+            latency_ms: completion.latency_ms,
+            cost_usd: self
+                .prices
+                .cost(&completion.model, usage.prompt_tokens, usage.completion_tokens),
+        };
+        self.record(event).await;
+    }
+
+    /// Records generated or edited images.
+    pub async fn record_images(&self, chat_guid: &str, sender: Option<&str>, count: usize, latency_ms: i64) {
+        let event = UsageEvent {
+            chat_guid: chat_guid.to_string(),
+            sender: sender.map(str::to_string),
+            provider: "openai".to_string(),
+            kind: "image".to_string(),
+            model: OPENAI_IMAGE_MODEL.to_string(),
+            prompt_tokens: 0,
+            completion_tokens: 0,
+            image_count: count as i64,
+            latency_ms,
+            cost_usd: count as f64 * self.image_price,
+        };
+        self.record(event).await;
+    }
+
+    async fn record(&self, event: UsageEvent) {
+        if let Err(e) = self.database.record_usage(&event).await {
+            error!("Failed to record usage for chat {}: {}", event.chat_guid, e);
+        }
+    }
+}
+
+/// The `@usage` report: totals, then per model, then the heaviest users.
+pub fn format_usage_report(period: &str, models: &[UsageSummary], senders: &[SenderUsage]) -> String {
+    if models.is_empty() {
+        return format!("No AI usage in this chat {}.", period);
+    }
+
+    let requests: i64 = models.iter().map(|m| m.requests).sum();
+    let tokens: i64 = models.iter().map(|m| m.tokens).sum();
+    let images: i64 = models.iter().map(|m| m.images).sum();
+    let cost: f64 = models.iter().map(|m| m.cost_usd).sum();
+
+    let mut lines = vec![
+        format!("Usage {}:", period),
+        format!(
+            "• {} request{}, {} tokens{}",
+            requests,
+            if requests == 1 { "" } else { "s" },
+            tokens,
+            if images > 0 { format!(", {} images", images) } else { String::new() }
+        ),
+        format!("• Estimated cost: ${:.2}", cost),
+        "By model:".to_string(),
+    ];
+    lines.extend(models.iter().map(|m| {
+        format!(
+            "• {} ({}): {} requests, {} tokens, ${:.2}, avg {:.1}s",
+            m.model,
+            m.provider,
+            m.requests,
+            m.tokens,
+            m.cost_usd,
+            m.avg_latency_ms as f64 / 1000.0
+        )
+    }));
+
+    if senders.iter().any(|s| s.sender.is_some()) {
+        lines.push("Top users:".to_string());
+        lines.extend(senders.iter().map(|s| {
+            format!(
+                "• {}: {} tokens, ${:.2}",
+                s.sender.as_deref().unwrap_or("the bot itself"),
+                s.tokens,
+                s.cost_usd
+            )
+        }));
+    }
+
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_usage_report() {
+        let models = vec![
+            UsageSummary {
+                provider: "openai".to_string(),
+                model: "gpt-4o".to_string(),
+                requests: 3,
+                tokens: 4200,
+                images: 0,
+                cost_usd: 0.0215,
+                avg_latency_ms: 1250,
+            },
+            UsageSummary {
+                provider: "openai".to_string(),
+                model: "gpt-image-1".to_string(),
+                requests: 1,
+                tokens: 0,
+                images: 2,
+                cost_usd: 0.08,
+                avg_latency_ms: 9000,
+            },
+        ];
+        let senders = vec![SenderUsage {
+            sender: Some("+15551234567".to_string()),
+            tokens: 4200,
+            cost_usd: 0.1015,
+        }];
+
+        let report = format_usage_report("today", &models, &senders);
+        assert!(report.starts_with("Usage today:\n• 4 requests, 4200 tokens, 2 images\n• Estimated cost: $0.10"));
+        assert!(report.contains("• gpt-4o (openai): 3 requests, 4200 tokens, $0.02, avg 1.2s"));
+        assert!(report.ends_with("Top users:\n• +15551234567: 4200 tokens, $0.10"));
+
+        assert_eq!(format_usage_report("this week", &[], &[]), "No AI usage in this chat this week.");
+    }
+}